@@ -1,22 +1,41 @@
+mod logs;
 mod scripts;
 mod tts;
 mod utils;
-use std::{collections::HashMap, path::Path, process::Command};
+use std::{collections::HashMap, path::Path};
 
 // Add clap for command line argument parsing
 use clap::{App, Arg};
+use lazy_static::lazy_static;
 
 // Add warp server imports
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex;
-use tts::config::{update_api_port, update_pitch, update_rate, update_volume};
+use tts::config::{
+    update_api_port, update_auto_voice_fallback, update_pitch, update_preferred_voice, update_rate,
+    update_voices_cache_ttl_hours, update_volume,
+};
 use warp::Filter;
 
+lazy_static! {
+    // The token the script routes check, replaced when the window regenerates it
+    static ref API_TOKEN: Arc<RwLock<String>> = Arc::new(RwLock::new(String::new()));
+}
+
+use logs::structs::{ExportFormat, LogLine, LogRule, LogSearch};
 use scripts::{
+    arguments::ArgumentError,
     disk::{add_script_to_disk, get_scripts_string, remove_script, save_script},
-    structs::{Script, ScriptSaveWindow},
-    terminal::{start_script, stop_script},
+    error::{report_error, ScriptError},
+    integrity::IntegrityReport,
+    pipelines::{Pipeline, PipelineRun},
+    scheduler::Schedule,
+    structs::{
+        RunRecord, RunningScript, Script, ScriptMeta, ScriptQuery, ScriptSaveLocal,
+        ScriptSaveWindow, ScriptStartOverrides, WatchRule,
+    },
+    terminal::stop_script_by_path,
 };
 use serde_json::{self};
 use tauri::{Emitter, Listener, Manager};
@@ -26,12 +45,17 @@ use tts::{
         TtsConfig,
     },
     disk::open_in_export_folder,
-    tts::{
-        check_audio_file_exists, generate_tts_synthesis, get_voices_list_names, save_voices_list,
+    language::{split_by_language, LanguageSegment},
+    lexicon::{LexiconEntry, NormalizedText},
+    tts::{check_audio_file_exists, generate_tts_synthesis},
+    voices::{
+        get_catalog_status, get_voices_list_names, refresh_voices_if_stale,
+        refresh_voices_in_background, VoiceCatalogStatus, VoiceInfo, VoiceQuery,
     },
 };
 use utils::{
     constants::WINDOW_LABEL,
+    secrets::SecretInfo,
     structs::{AddFile, AudioFile, AudioText, RemoveFile},
 };
 
@@ -45,25 +69,51 @@ struct ApiResponse {
     body: String,
 }
 
-fn open_power_shell_window(filepath: &str) {
-    let powershell_command = format!(
-        "Start-Process powershell.exe -ArgumentList \"-NoExit\", \"-Command Get-Content -Path '{}' -Wait\"",
-        filepath
-    );
+#[tauri::command]
+fn start_log_tail(path: String) -> Result<(), String> {
+    logs::tail::start_tail(&path).map_err(|e| e.to_string())
+}
 
-    Command::new("powershell")
-        .args(&["-Command", &powershell_command])
-        .spawn()
-        .expect("Failed to open PowerShell window");
+#[tauri::command]
+fn stop_log_tail(path: String) -> bool {
+    logs::tail::stop_tail(&path)
 }
 
 #[tauri::command]
-fn monitor_log_file(filepath: String) {
-    println!(
-        "Opening PowerShell window to monitor log file: {}",
-        filepath
-    );
-    open_power_shell_window(&filepath);
+fn get_log_tails() -> Vec<String> {
+    logs::tail::list_tails()
+}
+
+#[tauri::command]
+fn ack_log_lines(path: String) {
+    logs::tail::ack_lines(&path)
+}
+
+#[tauri::command]
+fn get_log_rules(path: String) -> Vec<LogRule> {
+    logs::filters::get_rules(&path)
+}
+
+#[tauri::command]
+fn export_log_lines(lines: Vec<LogLine>, format: ExportFormat) -> Result<String, String> {
+    logs::export::export_lines(&lines, format)
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_log_rules(path: String, rules: Vec<LogRule>) -> Result<(), String> {
+    logs::filters::save_rules(&path, rules).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn search_log(search: LogSearch) -> Result<String, String> {
+    logs::search::start_search(search).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn cancel_log_search(search_id: String) -> bool {
+    logs::search::cancel_search(&search_id)
 }
 
 #[tauri::command]
@@ -83,30 +133,46 @@ async fn make_api_request(
         _ => return Err("Unsupported HTTP method".to_string()),
     };
 
+    // Secret references in headers and body are resolved only for this request
+    let mut secrets = vec![];
+
     if let Some(headers) = headers {
         let mut header_map = HeaderMap::new();
         for (key, value) in headers {
+            let value =
+                utils::secrets::resolve_secrets(&value, &mut secrets).map_err(|e| e.to_string())?;
             header_map.insert(
                 HeaderName::from_bytes(key.as_bytes()).map_err(|e| e.to_string())?,
-                HeaderValue::from_str(&value).map_err(|e| e.to_string())?,
+                HeaderValue::from_str(&value)
+                    .map_err(|e| utils::secrets::redact(&e.to_string(), &secrets))?,
             );
         }
         request = request.headers(header_map);
     }
 
     if let Some(body) = body {
+        let body =
+            utils::secrets::resolve_secrets(&body, &mut secrets).map_err(|e| e.to_string())?;
         request = request.body(body);
     }
 
-    let response = request.send().await.map_err(|e| e.to_string())?;
+    let response = request
+        .send()
+        .await
+        .map_err(|e| utils::secrets::redact(&e.to_string(), &secrets))?;
 
     let status = response.status().as_u16();
     let headers = response
         .headers()
         .iter()
-        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                utils::secrets::redact(v.to_str().unwrap_or(""), &secrets),
+            )
+        })
         .collect();
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = utils::secrets::redact(&response.text().await.map_err(|e| e.to_string())?, &secrets);
 
     Ok(ApiResponse {
         status,
@@ -115,6 +181,21 @@ async fn make_api_request(
     })
 }
 
+#[tauri::command]
+fn save_secret(label: String, value: String, id: Option<String>) -> Result<String, String> {
+    utils::secrets::save_secret(&label, &value, id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_secret(id: String) -> Result<bool, String> {
+    utils::secrets::remove_secret(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_secrets() -> Vec<SecretInfo> {
+    utils::secrets::list_secrets()
+}
+
 #[tauri::command]
 async fn play_audio(name: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
     println!("⏯️ Play audio request received from UI with name: {}", name);
@@ -321,6 +402,147 @@ async fn get_audio_playback_status() -> serde_json::Value {
     })
 }
 
+#[tauri::command]
+fn start_script(script: Script) -> Result<String, String> {
+    scripts::terminal::start_script(&script).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn stop_script(run_id: String) -> Result<bool, String> {
+    tauri::async_runtime::spawn_blocking(move || scripts::terminal::stop_script(&run_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_running_scripts() -> Vec<RunningScript> {
+    scripts::process::list_running()
+}
+
+#[tauri::command]
+fn get_script_history(name: Option<String>) -> Vec<RunRecord> {
+    scripts::history::get_history(name.as_deref())
+}
+
+#[tauri::command]
+fn rerun_script(run_id: String, allow_changed: Option<bool>) -> Result<String, String> {
+    scripts::terminal::rerun_script(&run_id, allow_changed.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_script_run_log(run_id: String) -> Result<String, String> {
+    let record = scripts::history::get_history_entry(&run_id)
+        .ok_or_else(|| format!("Run {} not found in history", run_id))?;
+    let bytes = std::fs::read(&record.log_path).map_err(|e| e.to_string())?;
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+#[tauri::command]
+fn get_script_schedules() -> Vec<Schedule> {
+    scripts::scheduler::list_schedules()
+}
+
+#[tauri::command]
+fn save_script_schedule(schedule: Schedule) -> Result<Schedule, String> {
+    scripts::scheduler::upsert_schedule(schedule).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_script_schedule(id: String) -> Result<bool, String> {
+    scripts::scheduler::remove_schedule(&id).map_err(|e| e.to_string())
+}
+
+// Sends the saved scripts to the window, read failures go out as a scripts_error event
+fn emit_scripts_list(app_handle: &tauri::AppHandle) {
+    let scripts = match get_scripts_string() {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            report_error(&e);
+            return;
+        }
+    };
+
+    if let Some(window) = app_handle.get_webview_window(WINDOW_LABEL) {
+        window
+            .emit_to(WINDOW_LABEL, "scripts", scripts)
+            .unwrap_or_else(|e| eprintln!("Failed to emit scripts event: {}", e));
+    }
+}
+
+#[tauri::command]
+fn query_scripts(query: ScriptQuery) -> Result<Vec<ScriptSaveLocal>, String> {
+    scripts::disk::query_scripts(&query).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_script_meta(name: String, meta: ScriptMeta) -> Result<(), String> {
+    scripts::disk::update_script_meta(&name, meta).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_script_watch_rules(name: String, rules: Vec<WatchRule>) -> Result<(), String> {
+    scripts::disk::set_watch_rules(&name, rules).map_err(|e| e.to_string())?;
+    scripts::watch::reload_watchers();
+    Ok(())
+}
+
+#[tauri::command]
+fn get_script_integrity() -> Vec<IntegrityReport> {
+    scripts::integrity::check_scripts()
+}
+
+#[tauri::command]
+fn update_script_copy(name: String) -> Result<(), String> {
+    scripts::disk::update_script_copy(&name).map_err(|e| e.to_string())?;
+    scripts::watch::reload_watchers();
+    Ok(())
+}
+
+#[tauri::command]
+fn accept_script_changes(name: String) -> Result<(), String> {
+    scripts::disk::accept_script_changes(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_pipelines() -> Vec<Pipeline> {
+    scripts::pipelines::list_pipelines()
+}
+
+#[tauri::command]
+fn save_pipeline(pipeline: Pipeline) -> Result<(), String> {
+    scripts::pipelines::save_pipeline(pipeline).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_pipeline(name: String) -> Result<bool, String> {
+    scripts::pipelines::remove_pipeline(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn run_pipeline(name: String) -> Result<String, String> {
+    scripts::pipelines::start_pipeline(&name, "manual").map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_pipeline_run(pipeline_run_id: String) -> Option<PipelineRun> {
+    scripts::pipelines::get_pipeline_run(&pipeline_run_id)
+}
+
+#[tauri::command]
+fn get_api_token() -> Result<String, String> {
+    tts::config::ensure_api_token().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn regenerate_api_token() -> Result<String, String> {
+    let token = tts::config::regenerate_api_token().map_err(|e| e.to_string())?;
+    *API_TOKEN.write().unwrap() = token.clone();
+    Ok(token)
+}
+
 #[tauri::command]
 fn check_audio_exists(name: String) -> bool {
     let file_name = if name.is_empty() {
@@ -376,6 +598,94 @@ fn set_tts_api_port(port: u16) -> Result<(), String> {
     update_api_port(port).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn set_tts_voices_cache_ttl(hours: u64) -> Result<(), String> {
+    update_voices_cache_ttl_hours(hours).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_tts_preferred_voice(language: String, voice: Option<String>) -> Result<(), String> {
+    update_preferred_voice(&language, voice.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_tts_auto_voice_fallback(voice: String) -> Result<(), String> {
+    update_auto_voice_fallback(&voice).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn detect_text_language(text: String) -> Vec<LanguageSegment> {
+    let config = load_config();
+    split_by_language(&text, &config)
+}
+
+#[tauri::command]
+fn get_tts_lexicon() -> Vec<LexiconEntry> {
+    tts::lexicon::load_lexicon()
+}
+
+#[tauri::command]
+fn save_tts_lexicon_entry(entry: LexiconEntry) -> Result<LexiconEntry, String> {
+    tts::lexicon::upsert_lexicon_entry(entry).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_tts_lexicon_entry(id: String) -> Result<bool, String> {
+    tts::lexicon::remove_lexicon_entry(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn normalize_tts_text(text: String, voice: String) -> NormalizedText {
+    tts::lexicon::normalize_for_voice(&text, &voice)
+}
+
+#[tauri::command]
+fn get_voices_catalog_status() -> VoiceCatalogStatus {
+    get_catalog_status()
+}
+
+#[tauri::command]
+async fn preview_voice(
+    name: String,
+    play: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    println!("🎧 Preview request received for voice: {}", name);
+
+    let path = tts::preview::preview_voice(&name).map_err(|e| e.to_string())?;
+    let file_path = path.to_string_lossy().to_string();
+
+    if play {
+        tts::tts::play_audio_backend(&file_path)?;
+        app_handle
+            .emit(
+                "audio_playback_status",
+                serde_json::json!({
+                    "status": "playing",
+                    "file": path.file_name().map(|name| name.to_string_lossy().to_string())
+                }),
+            )
+            .unwrap_or_else(|e| eprintln!("Failed to emit audio status: {}", e));
+    }
+
+    Ok(file_path)
+}
+
+#[tauri::command]
+fn search_voices(
+    locale: Option<String>,
+    language: Option<String>,
+    gender: Option<String>,
+    text: Option<String>,
+) -> Vec<VoiceInfo> {
+    tts::voices::search_voices(&VoiceQuery {
+        locale,
+        language,
+        gender,
+        text,
+    })
+}
+
 #[tauri::command]
 fn is_api_server_running() -> bool {
     let config = tts::config::load_config();
@@ -401,6 +711,26 @@ fn get_api_server_status() -> serde_json::Value {
     })
 }
 
+// Sends the refreshed voices list to the window, or the error when the refresh failed
+fn emit_voices_refresh_result(app_handle: &tauri::AppHandle, result: Result<(), String>) {
+    let Some(window) = app_handle.get_webview_window(WINDOW_LABEL) else {
+        return;
+    };
+
+    match result {
+        Ok(_) => window
+            .emit_to(
+                WINDOW_LABEL,
+                "get_voices_list_response",
+                get_voices_list_names(),
+            )
+            .unwrap_or_else(|e| eprintln!("Failed to emit voices list: {}", e)),
+        Err(error) => window
+            .emit_to(WINDOW_LABEL, "voices_list_error", error)
+            .unwrap_or_else(|e| eprintln!("Failed to emit voices list error: {}", e)),
+    }
+}
+
 // Add API server module to lib.rs
 async fn start_api_server(app_handle: tauri::AppHandle) {
     // Get port from config, defaulting to 7891 if loading fails
@@ -411,12 +741,23 @@ async fn start_api_server(app_handle: tauri::AppHandle) {
     // Store app_handle for use in routes
     let app_handle = Arc::new(Mutex::new(app_handle));
 
+    // Script routes run programs on this machine, so they need the token from the config
+    let token = tts::config::ensure_api_token().unwrap_or_else(|e| {
+        eprintln!(
+            "❌ Failed to create API token, script routes are disabled: {}",
+            e
+        );
+        String::new()
+    });
+    *API_TOKEN.write().unwrap() = token;
+    let api_token = API_TOKEN.clone();
+
     // Create a CORS layer to allow requests from anywhere
     let make_cors = || {
         warp::cors()
             .allow_any_origin()
-            .allow_headers(vec!["content-type"])
-            .allow_methods(vec!["POST"])
+            .allow_headers(vec!["content-type", "authorization", "x-api-token"])
+            .allow_methods(vec!["GET", "POST", "DELETE"])
     };
 
     // Define route for text-to-speech with proper error handling
@@ -478,17 +819,379 @@ async fn start_api_server(app_handle: tauri::AppHandle) {
         })
         .with(make_cors()); // Apply CORS to the route
 
+    // Define route for searching the voice catalog
+    let voices_route = warp::path("voices")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<VoiceQuery>())
+        .map(|query: VoiceQuery| {
+            println!("🌐 Received voices API request: {:?}", query);
+            warp::reply::json(&tts::voices::search_voices(&query))
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    // Define route for auditioning a voice, returns the audio unless asked to play it
+    let preview_route = warp::path!("voices" / String / "preview")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_app_handle(app_handle.clone()))
+        .and_then(|name, query, handle| async move {
+            println!("🌐 Received voice preview API request");
+            handle_preview_request(name, query, handle).await
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    // Define routes for managing the pronunciation lexicon
+    let lexicon_list_route = warp::path("lexicon")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(|| warp::reply::json(&tts::lexicon::load_lexicon()))
+        .with(make_cors()); // Apply CORS to the route
+
+    let lexicon_save_route = warp::path("lexicon")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(|entry: LexiconEntry| {
+            println!("🌐 Received lexicon save API request");
+            match tts::lexicon::upsert_lexicon_entry(entry) {
+                Ok(entry) => {
+                    warp::reply::with_status(warp::reply::json(&entry), warp::http::StatusCode::OK)
+                }
+                Err(e) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": e.to_string()
+                    })),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ),
+            }
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    let lexicon_remove_route = warp::path!("lexicon" / String)
+        .and(warp::delete())
+        .map(|id: String| {
+            println!("🌐 Received lexicon remove API request");
+            match tts::lexicon::remove_lexicon_entry(&id) {
+                Ok(true) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "success": true
+                    })),
+                    warp::http::StatusCode::OK,
+                ),
+                Ok(false) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": "Lexicon entry not found"
+                    })),
+                    warp::http::StatusCode::NOT_FOUND,
+                ),
+                Err(e) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": e.to_string()
+                    })),
+                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                ),
+            }
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    // Define route that shows the text after the lexicon was applied
+    let normalize_route = warp::path("normalize")
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(|payload: serde_json::Value| {
+            let text = payload
+                .get("text")
+                .and_then(|text| text.as_str())
+                .unwrap_or("");
+            let voice = match payload.get("voice").and_then(|voice| voice.as_str()) {
+                Some(voice) => voice.to_string(),
+                None => tts::config::load_config().last_voice,
+            };
+            warp::reply::json(&tts::lexicon::normalize_for_voice(text, &voice))
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    // Define routes for running script pipelines
+    let pipelines_route = warp::path("pipelines")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_token(api_token.clone()))
+        .map(|| warp::reply::json(&scripts::pipelines::list_pipelines()))
+        .with(make_cors()); // Apply CORS to the route
+
+    let pipeline_run_route = warp::path!("pipelines" / String / "run")
+        .and(warp::post())
+        .and(with_api_token(api_token.clone()))
+        .map(|name: String| {
+            println!("🌐 Received pipeline run API request: {}", name);
+            match scripts::pipelines::start_pipeline(&name, "api") {
+                Ok(pipeline_run_id) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "pipeline_run_id": pipeline_run_id
+                    })),
+                    warp::http::StatusCode::OK,
+                ),
+                Err(e) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": e.to_string()
+                    })),
+                    warp::http::StatusCode::NOT_FOUND,
+                ),
+            }
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    let pipeline_status_route = warp::path!("pipelines" / "runs" / String)
+        .and(warp::get())
+        .and(with_api_token(api_token.clone()))
+        .map(|pipeline_run_id: String| {
+            match scripts::pipelines::get_pipeline_run(&pipeline_run_id) {
+                Some(run) => {
+                    warp::reply::with_status(warp::reply::json(&run), warp::http::StatusCode::OK)
+                }
+                None => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": "Pipeline run not found"
+                    })),
+                    warp::http::StatusCode::NOT_FOUND,
+                ),
+            }
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    // Define routes for running saved scripts remotely
+    let scripts_route = warp::path("scripts")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_api_token(api_token.clone()))
+        .map(|| warp::reply::json(&scripts::disk::list_scripts()))
+        .with(make_cors()); // Apply CORS to the route
+
+    let script_start_route = warp::path!("scripts" / String / "start")
+        .and(warp::post())
+        .and(with_api_token(api_token.clone()))
+        .and(warp::body::bytes())
+        .map(|name: String, body: warp::hyper::body::Bytes| {
+            println!("🌐 Received script start API request: {}", name);
+            let overrides = if body.is_empty() {
+                Ok(ScriptStartOverrides::default())
+            } else {
+                serde_json::from_slice::<ScriptStartOverrides>(&body)
+            };
+
+            let result = overrides
+                .map_err(|e| e.to_string())
+                .and_then(|overrides| {
+                    scripts::terminal::saved_script(
+                        &name,
+                        overrides.hidden,
+                        overrides.arguments,
+                        overrides.values,
+                    )
+                    .map_err(|e| e.to_string())
+                })
+                .and_then(|script| {
+                    scripts::terminal::start_script_with_trigger(&script, "api")
+                        .map_err(|e| e.to_string())
+                });
+
+            match result {
+                Ok(run_id) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "run_id": run_id
+                    })),
+                    warp::http::StatusCode::OK,
+                ),
+                Err(e) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": e
+                    })),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ),
+            }
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    let run_stop_route = warp::path!("runs" / String / "stop")
+        .and(warp::post())
+        .and(with_api_token(api_token.clone()))
+        .and_then(|run_id: String| async move {
+            println!("🌐 Received script stop API request: {}", run_id);
+            let result = tauri::async_runtime::spawn_blocking(move || {
+                scripts::terminal::stop_script(&run_id)
+            })
+            .await;
+
+            let reply = match result {
+                Ok(Ok(stopped)) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "stopped": stopped
+                    })),
+                    warp::http::StatusCode::OK,
+                ),
+                Ok(Err(e)) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": e.to_string()
+                    })),
+                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                ),
+                Err(e) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": e.to_string()
+                    })),
+                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                ),
+            };
+            Ok::<_, warp::Rejection>(reply)
+        })
+        .with(make_cors()); // Apply CORS to the route
+
+    let run_status_route = warp::path!("runs" / String)
+        .and(warp::get())
+        .and(with_api_token(api_token.clone()))
+        .map(
+            |run_id: String| match scripts::history::get_history_entry(&run_id) {
+                Some(record) => {
+                    let running = scripts::process::list_running()
+                        .into_iter()
+                        .find(|running| running.run_id == run_id);
+                    warp::reply::with_status(
+                        warp::reply::json(&serde_json::json!({
+                            "run": record,
+                            "running": running
+                        })),
+                        warp::http::StatusCode::OK,
+                    )
+                }
+                None => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": "Run not found"
+                    })),
+                    warp::http::StatusCode::NOT_FOUND,
+                ),
+            },
+        )
+        .with(make_cors()); // Apply CORS to the route
+
+    // Poll with ?from=<next> to follow the output of a running script
+    let run_output_route = warp::path!("runs" / String / "output")
+        .and(warp::get())
+        .and(with_api_token(api_token.clone()))
+        .and(warp::query::<HashMap<String, String>>())
+        .map(|run_id: String, query: HashMap<String, String>| {
+            let from = query
+                .get("from")
+                .and_then(|from| from.parse::<usize>().ok())
+                .unwrap_or(0);
+            match scripts::history::read_run_log(&run_id, from) {
+                Ok((lines, next)) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "lines": lines,
+                        "next": next,
+                        "running": scripts::process::is_running(&run_id)
+                    })),
+                    warp::http::StatusCode::OK,
+                ),
+                Err(e) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({
+                        "error": e.to_string()
+                    })),
+                    warp::http::StatusCode::NOT_FOUND,
+                ),
+            }
+        })
+        .with(make_cors()); // Apply CORS to the route
+
     println!("🌐 Starting API server on http://{}", addr);
     println!("🔊 TTS endpoint available at http://{}/tts", addr);
     println!("⏹️ Stop endpoint available at http://{}/stop", addr);
+    println!("🗣️ Voices endpoint available at http://{}/voices", addr);
+    println!(
+        "🎧 Preview endpoint available at http://{}/voices/<name>/preview",
+        addr
+    );
+    println!("🔤 Lexicon endpoint available at http://{}/lexicon", addr);
+    println!(
+        "🔤 Normalize endpoint available at http://{}/normalize",
+        addr
+    );
+    println!("📜 Scripts endpoint available at http://{}/scripts", addr);
+    println!("📜 Runs endpoint available at http://{}/runs/<id>", addr);
+    println!(
+        "🔗 Pipelines endpoint available at http://{}/pipelines",
+        addr
+    );
+    println!("🔑 Script and pipeline routes need the api_token from the config");
     println!("🔓 CORS is disabled - API accessible from any domain");
 
     // Combine routes
-    let routes = tts_route.or(stop_route);
+    let routes = tts_route
+        .or(stop_route)
+        .or(voices_route)
+        .or(preview_route)
+        .or(lexicon_list_route)
+        .or(lexicon_save_route)
+        .or(lexicon_remove_route)
+        .or(normalize_route)
+        .or(pipelines_route)
+        .or(pipeline_run_route)
+        .or(pipeline_status_route)
+        .or(scripts_route)
+        .or(script_start_route)
+        .or(run_stop_route)
+        .or(run_output_route)
+        .or(run_status_route)
+        .recover(handle_rejection);
 
     warp::serve(routes).run(addr).await;
 }
 
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+// Accepts the token as "Authorization: Bearer <token>" or "X-Api-Token: <token>",
+// an empty token rejects every request
+fn with_api_token(
+    token: Arc<RwLock<String>>,
+) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::header::optional::<String>("x-api-token"))
+        .and_then(
+            move |authorization: Option<String>, api_token: Option<String>| {
+                // Read on every request so a regenerated token applies right away
+                let token = token.read().unwrap().clone();
+                async move {
+                    let provided = authorization
+                        .as_deref()
+                        .and_then(|value| value.strip_prefix("Bearer "))
+                        .map(|value| value.to_string())
+                        .or(api_token);
+                    match provided {
+                        Some(provided) if !token.is_empty() && provided == token => Ok(()),
+                        _ => Err(warp::reject::custom(Unauthorized)),
+                    }
+                }
+            },
+        )
+        .untuple_one()
+}
+
+async fn handle_rejection(rejection: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+    if rejection.find::<Unauthorized>().is_some() {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "error": "Invalid or missing API token"
+            })),
+            warp::http::StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    Err(rejection)
+}
+
 // Helper function to share app_handle with routes
 fn with_app_handle(
     app_handle: Arc<Mutex<tauri::AppHandle>>,
@@ -547,9 +1250,15 @@ async fn handle_tts_request(
         ));
     }
 
-    // Load the updated config for voice info
+    // Load the updated config for voice info, the request may pick its own voice or "auto"
     let config = tts::config::load_config();
-    println!("📚 Using voice: {}", config.last_voice);
+    let voice = payload
+        .get("voice")
+        .and_then(|voice| voice.as_str())
+        .filter(|voice| !voice.is_empty())
+        .unwrap_or(&config.last_voice)
+        .to_string();
+    println!("📚 Using voice: {}", voice);
 
     // Make sure the export directory exists before we try to generate audio
     let play_file = tts::constants::PLAY_MODE_AUDIO_FILE;
@@ -560,7 +1269,7 @@ async fn handle_tts_request(
     let result = tts::tts::generate_tts_synthesis(
         text,
         play_file,
-        &config.last_voice,
+        &voice,
         config.pitch,
         config.rate,
         config.volume,
@@ -700,6 +1409,77 @@ async fn handle_tts_request(
     }
 }
 
+// Voice preview request handler
+async fn handle_preview_request(
+    name: String,
+    query: HashMap<String, String>,
+    app_handle: Arc<Mutex<tauri::AppHandle>>,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let play = query
+        .get("play")
+        .map(|value| value == "1" || value.to_lowercase() == "true")
+        .unwrap_or(false);
+
+    let path = match tts::preview::preview_voice(&name) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("❌ Failed to generate voice preview: {}", e);
+            return Ok(Box::new(warp::reply::with_status(
+                warp::reply::json(&serde_json::json!({
+                    "error": format!("Failed to generate preview: {}", e)
+                })),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            )));
+        }
+    };
+
+    if play {
+        let file_path = path.to_string_lossy().to_string();
+        if let Err(e) = tts::tts::play_audio_backend(&file_path) {
+            return Ok(Box::new(warp::reply::with_status(
+                warp::reply::json(&serde_json::json!({
+                    "error": format!("Failed to play preview: {}", e)
+                })),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            )));
+        }
+
+        app_handle
+            .lock()
+            .await
+            .emit(
+                "audio_playback_status",
+                serde_json::json!({
+                    "status": "playing",
+                    "file": path.file_name().map(|name| name.to_string_lossy().to_string())
+                }),
+            )
+            .unwrap_or_else(|e| eprintln!("Failed to emit audio status: {}", e));
+
+        return Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "success": true,
+                "message": "Voice preview playing"
+            })),
+            warp::http::StatusCode::OK,
+        )));
+    }
+
+    match std::fs::read(&path) {
+        Ok(bytes) => Ok(Box::new(warp::reply::with_header(
+            bytes,
+            "content-type",
+            "audio/mpeg",
+        ))),
+        Err(e) => Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "error": format!("Failed to read preview: {}", e)
+            })),
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+        ))),
+    }
+}
+
 // Stop audio request handler
 async fn handle_stop_request(
     app_handle: Arc<Mutex<tauri::AppHandle>>,
@@ -768,8 +1548,36 @@ pub fn run(args: Vec<String>) {
                 .help("Run in background mode (API server only, no UI)")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("run-pipeline")
+                .long("run-pipeline")
+                .value_name("NAME")
+                .help("Run a script pipeline, then exit with its result (no UI)")
+                .takes_value(true),
+        )
         .get_matches_from(args);
 
+    // Pipelines from the command line run to completion without starting the app
+    if let Some(name) = matches.value_of("run-pipeline") {
+        println!("🔗 Running pipeline {}", name);
+        let succeeded = match scripts::pipelines::run_pipeline_blocking(name, "cli") {
+            Ok(run) => {
+                for step in &run.steps {
+                    println!(
+                        "   - {}: {} (exit code {:?})",
+                        step.script_name, step.status, step.exit_code
+                    );
+                }
+                run.status == "succeeded"
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to run pipeline: {}", e);
+                false
+            }
+        };
+        std::process::exit(if succeeded { 0 } else { 1 });
+    }
+
     // Check both command line arguments and environment variables
     let api_env = std::env::var("ENABLE_API")
         .map(|val| val == "1" || val.to_lowercase() == "true")
@@ -832,7 +1640,18 @@ pub fn run(args: Vec<String>) {
     builder = builder
         .invoke_handler(tauri::generate_handler![
             make_api_request,
-            monitor_log_file,
+            save_secret,
+            remove_secret,
+            list_secrets,
+            start_log_tail,
+            stop_log_tail,
+            get_log_tails,
+            ack_log_lines,
+            get_log_rules,
+            save_log_rules,
+            export_log_lines,
+            search_log,
+            cancel_log_search,
             play_audio,
             check_audio_exists,
             get_tts_config,
@@ -848,6 +1667,39 @@ pub fn run(args: Vec<String>) {
             get_audio_playback_status,
             is_api_server_running,
             get_api_server_status,
+            search_voices,
+            preview_voice,
+            get_voices_catalog_status,
+            set_tts_voices_cache_ttl,
+            set_tts_preferred_voice,
+            set_tts_auto_voice_fallback,
+            detect_text_language,
+            get_tts_lexicon,
+            save_tts_lexicon_entry,
+            remove_tts_lexicon_entry,
+            normalize_tts_text,
+            start_script,
+            stop_script,
+            list_running_scripts,
+            get_script_history,
+            rerun_script,
+            get_script_run_log,
+            get_script_schedules,
+            save_script_schedule,
+            remove_script_schedule,
+            query_scripts,
+            update_script_meta,
+            set_script_watch_rules,
+            get_script_integrity,
+            update_script_copy,
+            accept_script_changes,
+            get_pipelines,
+            save_pipeline,
+            remove_pipeline,
+            run_pipeline,
+            get_pipeline_run,
+            get_api_token,
+            regenerate_api_token,
         ])
         .plugin(tauri_plugin_dialog::init())
         .setup(move |app| {
@@ -876,9 +1728,12 @@ pub fn run(args: Vec<String>) {
                 let save_script_file = app_handle.clone();
                 let remove_script_file = app_handle.clone();
                 let add_script_file = app_handle.clone();
+                let start_script_handle = app_handle.clone();
 
                 // Initialize the scripts database
-                let _ = scripts::disk::get_scripts_db_path();
+                if let Err(e) = scripts::disk::ensure_scripts_db() {
+                    report_error(&e);
+                }
 
                 // Register event listeners
                 app.listen("update_title", move |event| {
@@ -900,14 +1755,10 @@ pub fn run(args: Vec<String>) {
                 });
 
                 app.listen("refresh_voices_list", move |_| {
-                    save_voices_list();
-                    let voices = get_voices_list_names();
-                    let window = refresh_hvoices_handle
-                        .get_webview_window(WINDOW_LABEL)
-                        .unwrap();
-                    window
-                        .emit_to(WINDOW_LABEL, "get_voices_list_response", voices)
-                        .expect("Failed to emit event");
+                    let handle = refresh_hvoices_handle.clone();
+                    refresh_voices_in_background(move |result| {
+                        emit_voices_refresh_result(&handle, result);
+                    });
                 });
 
                 app.listen("create_audio_from_text", move |event| {
@@ -956,31 +1807,25 @@ pub fn run(args: Vec<String>) {
                 });
 
                 app.listen("get_scripts", move |_| {
-                    let scripts = get_scripts_string().expect("Failed to get scripts");
-                    get_scripts
-                        .get_webview_window(WINDOW_LABEL)
-                        .unwrap()
-                        .emit_to(WINDOW_LABEL, "scripts", scripts)
-                        .expect("Failed to emit event");
+                    emit_scripts_list(&get_scripts);
                 });
 
                 app.listen("save_script", move |event| {
                     let value = event.payload();
                     match serde_json::from_str::<ScriptSaveWindow>(value) {
                         Ok(window_script) => {
-                            save_script(
+                            match save_script(
                                 window_script.path,
                                 window_script.name,
                                 window_script.script_args,
                                 window_script.save,
-                            )
-                            .unwrap();
-                            let scripts = get_scripts_string().expect("Failed to get scripts");
-                            save_script_file
-                                .get_webview_window(WINDOW_LABEL)
-                                .unwrap()
-                                .emit_to(WINDOW_LABEL, "scripts", scripts)
-                                .expect("Failed to emit event");
+                                window_script.options,
+                                window_script.meta,
+                            ) {
+                                Ok(_) => scripts::watch::reload_watchers(),
+                                Err(e) => report_error(&e),
+                            }
+                            emit_scripts_list(&save_script_file);
                         }
                         Err(e) => eprintln!("Failed to parse event payload: {}", e),
                     }
@@ -990,38 +1835,95 @@ pub fn run(args: Vec<String>) {
                     let value = event.payload();
                     match serde_json::from_str::<Script>(value) {
                         Ok(window_script) => {
-                            start_script(&Script {
-                                visibility: window_script.visibility,
-                                arguments: window_script.arguments,
-                                path: window_script.path,
-                            })
-                            .unwrap();
+                            match scripts::terminal::start_script(&window_script) {
+                                Ok(run_id) => {
+                                    start_script_handle
+                                        .get_webview_window(WINDOW_LABEL)
+                                        .unwrap()
+                                        .emit_to(
+                                            WINDOW_LABEL,
+                                            "script_started",
+                                            serde_json::json!({
+                                                "run_id": run_id,
+                                                "path": window_script.path
+                                            }),
+                                        )
+                                        .unwrap_or_else(|e| {
+                                            eprintln!("Failed to emit script_started event: {}", e)
+                                        });
+                                }
+                                Err(e) => {
+                                    // Ask the window for the {prompt} values and let it start the script again
+                                    let prompts = e.get_ref().and_then(|inner| match inner
+                                        .downcast_ref::<ArgumentError>(
+                                    ) {
+                                        Some(ArgumentError::PromptRequired(labels)) => {
+                                            Some(labels.clone())
+                                        }
+                                        _ => None,
+                                    });
+                                    // A changed file is started again once the window confirms it
+                                    let changed = e.get_ref().map_or(false, |inner| {
+                                        matches!(
+                                            inner.downcast_ref::<ScriptError>(),
+                                            Some(ScriptError::ContentChanged { .. })
+                                        )
+                                    });
+                                    match prompts {
+                                        Some(labels) => scripts::events::emit(
+                                            "script_prompt_required",
+                                            serde_json::json!({
+                                                "path": window_script.path,
+                                                "name": window_script.name,
+                                                "labels": labels
+                                            }),
+                                        ),
+                                        None if changed => scripts::events::emit(
+                                            "script_changed",
+                                            serde_json::json!({
+                                                "path": window_script.path,
+                                                "name": window_script.name,
+                                                "message": e.to_string()
+                                            }),
+                                        ),
+                                        None => eprintln!("Failed to start script: {}", e),
+                                    }
+                                }
+                            }
                         }
                         Err(e) => eprintln!("Failed to parse event payload: {}", e),
                     }
                 });
 
+                // The payload is a run id, or a script path to stop every run of that script
                 app.listen("stop_script", move |event| {
                     let payload = event.payload().to_string();
-                    let path = payload.trim_start_matches('"').trim_end_matches('"');
-                    let name = Path::new(path).file_name().unwrap().to_str().unwrap();
-                    stop_script(name).unwrap();
+                    let target = payload
+                        .trim_start_matches('"')
+                        .trim_end_matches('"')
+                        .to_string();
+
+                    // Stopping waits for the process to exit, keep it off the event thread
+                    std::thread::spawn(move || {
+                        let result = if scripts::process::is_running(&target) {
+                            scripts::terminal::stop_script(&target)
+                        } else {
+                            stop_script_by_path(&target)
+                        };
+                        if let Err(e) = result {
+                            eprintln!("Failed to stop script: {}", e);
+                        }
+                    });
                 });
 
                 app.listen("add_script", move |event| {
                     let payload = event.payload();
                     match serde_json::from_str::<AddFile>(payload) {
                         Ok(value) => {
-                            let path = value.path;
-                            add_script_to_disk(path);
-
-                            let scripts = get_scripts_string().expect("Failed to get scripts");
-                            // Use the app_handle to get the window by its label
-                            add_script_file
-                                .get_webview_window(WINDOW_LABEL)
-                                .unwrap()
-                                .emit_to(WINDOW_LABEL, "scripts", scripts)
-                                .expect("Failed to emit event");
+                            if let Err(e) = add_script_to_disk(&value.path) {
+                                report_error(&e);
+                            }
+                            emit_scripts_list(&add_script_file);
                         }
                         Err(e) => eprintln!("Failed to parse event payload: {}", e),
                     }
@@ -1031,18 +1933,11 @@ pub fn run(args: Vec<String>) {
                     let payload = event.payload();
                     match serde_json::from_str::<RemoveFile>(payload) {
                         Ok(value) => {
-                            let name = value.name;
-                            let path = value.path;
-                            let remove_from_disk = value.remove_from_disk;
-                            remove_script(name, path, remove_from_disk).unwrap();
-
-                            let scripts = get_scripts_string().expect("Failed to get scripts");
-                            // Use the app_handle to get the window by its label
-                            remove_script_file
-                                .get_webview_window(WINDOW_LABEL)
-                                .unwrap()
-                                .emit_to(WINDOW_LABEL, "scripts", scripts)
-                                .expect("Failed to emit event");
+                            match remove_script(&value.name, &value.path, value.remove_from_disk) {
+                                Ok(_) => scripts::watch::reload_watchers(),
+                                Err(e) => report_error(&e),
+                            }
+                            emit_scripts_list(&remove_script_file);
                         }
                         Err(e) => eprintln!("Failed to parse event payload: {}", e),
                     }
@@ -1102,6 +1997,26 @@ pub fn run(args: Vec<String>) {
                 println!("✅ Background mode: Minimal setup completed");
             }
 
+            // Forward script events such as output lines to the window
+            let scripts_events_handle = app_handle.clone();
+            scripts::events::set_emitter(Arc::new(move |event, payload| {
+                if let Some(window) = scripts_events_handle.get_webview_window(WINDOW_LABEL) {
+                    window
+                        .emit_to(WINDOW_LABEL, event, payload)
+                        .unwrap_or_else(|e| eprintln!("Failed to emit {} event: {}", event, e));
+                }
+            }));
+
+            // Scheduled scripts run in both modes, the emitter above must be set first
+            scripts::scheduler::start_scheduler();
+            scripts::watch::reload_watchers();
+
+            // Refresh the cached voices in the background once they are older than the TTL
+            let stale_voices_handle = app_handle.clone();
+            refresh_voices_if_stale(move |result| {
+                emit_voices_refresh_result(&stale_voices_handle, result);
+            });
+
             // Handle API server startup - regardless of mode
             if start_api {
                 println!("🌐 Starting API server...");
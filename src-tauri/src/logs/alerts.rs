@@ -0,0 +1,120 @@
+use lazy_static::lazy_static;
+use notify_rust::Notification;
+use rodio::{Decoder, OutputStream, Sink};
+use std::io::Cursor;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use super::constants::{LOG_SPEECH_QUEUE, LOG_WEBHOOK_TIMEOUT_SECS};
+use super::structs::{AlertTarget, LogLine, LogRule};
+use crate::scripts::events::emit;
+use crate::tts::config::load_config;
+use crate::tts::tts::synthesize_audio;
+
+lazy_static! {
+    // Queue of the speaker thread, started with the first spoken alert
+    static ref SPEECH: Mutex<Option<SyncSender<String>>> = Mutex::new(None);
+    // Shared by every webhook alert, the timeout keeps a hanging endpoint from piling up tasks
+    static ref WEBHOOK_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(LOG_WEBHOOK_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default();
+}
+
+// Synthesized into memory and played on an output stream of its own, so an alert
+// neither stops what the user is playing nor touches a file that may be open
+fn speak(text: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = load_config();
+    let audio_bytes = synthesize_audio(
+        text,
+        &config.last_voice,
+        config.pitch,
+        config.rate,
+        config.volume,
+    )?;
+
+    let (_stream, stream_handle) = OutputStream::try_default()?;
+    let sink = Sink::try_new(&stream_handle)?;
+    sink.append(Decoder::new(Cursor::new(audio_bytes))?);
+    sink.sleep_until_end();
+
+    Ok(())
+}
+
+// Spoken alerts take turns on one thread instead of cutting each other off mid sentence,
+// alerts that arrive while the queue is full are not spoken
+fn queue_speech(text: String) {
+    let mut speech = SPEECH.lock().unwrap();
+    let sender = speech.get_or_insert_with(|| {
+        let (sender, receiver) = mpsc::sync_channel::<String>(LOG_SPEECH_QUEUE);
+        thread::spawn(move || {
+            for text in receiver {
+                if let Err(e) = speak(&text) {
+                    println!("Log Alert Failed - speech ({})", e);
+                }
+            }
+        });
+        sender
+    });
+
+    match sender.try_send(text) {
+        Ok(_) => {}
+        Err(TrySendError::Full(_)) => println!("Log Alert Skipped - speech queue is full"),
+        Err(TrySendError::Disconnected(_)) => {
+            println!("Log Alert Failed - speech thread stopped");
+            // The next alert starts a new one
+            *speech = None;
+        }
+    }
+}
+
+// Every target runs on its own so a slow webhook never holds up the tail
+pub fn raise_alert(path: &str, rule: &LogRule, line: &LogLine) {
+    let title = if rule.name.is_empty() {
+        format!("Log alert in {}", path)
+    } else {
+        rule.name.clone()
+    };
+    println!("Log Alert - {} matched in {}", title, path);
+
+    let payload = serde_json::json!({
+        "path": path,
+        "rule": rule.name,
+        "line": line.text,
+        "level": line.level,
+        "timestamp": line.timestamp
+    });
+    emit("log_alert", payload.clone());
+
+    for target in rule.alerts.iter().cloned() {
+        let title = title.clone();
+        let text = line.text.clone();
+        let payload = payload.clone();
+
+        match target {
+            AlertTarget::Notification => {
+                thread::spawn(move || {
+                    if let Err(e) = Notification::new().summary(&title).body(&text).show() {
+                        println!("Log Alert Failed - notification ({})", e);
+                    }
+                });
+            }
+            AlertTarget::Speak => queue_speech(text),
+            AlertTarget::Webhook { url } => {
+                tauri::async_runtime::spawn(async move {
+                    let response = WEBHOOK_CLIENT
+                        .post(&url)
+                        .header("content-type", "application/json")
+                        .body(payload.to_string())
+                        .send()
+                        .await;
+                    if let Err(e) = response.and_then(|response| response.error_for_status()) {
+                        println!("Log Alert Failed - webhook {} ({})", url, e);
+                    }
+                });
+            }
+        }
+    }
+}
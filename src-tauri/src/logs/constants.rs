@@ -0,0 +1,23 @@
+// Milliseconds between checks of a followed file for new lines
+pub const LOG_TAIL_TICK_MILLIS: u64 = 250;
+// Lines sent to the window in one log_lines event
+pub const LOG_BATCH_LINES: usize = 500;
+// Batches the window may have unacknowledged before reading pauses
+pub const LOG_MAX_UNACKED_BATCHES: usize = 4;
+// Seconds without an acknowledgement after which the window is assumed to be gone
+pub const LOG_ACK_TIMEOUT_SECS: u64 = 5;
+// How far from the end a newly followed file starts, so the window shows some history
+pub const LOG_INITIAL_BYTES: u64 = 64 * 1024;
+// A line without a newline is sent anyway once it gets this long
+pub const LOG_MAX_LINE_BYTES: usize = 64 * 1024;
+pub const LOG_RULES_FILE_NAME: &str = "log-rules.json";
+// Seconds a webhook alert waits for the endpoint before it gives up
+pub const LOG_WEBHOOK_TIMEOUT_SECS: u64 = 10;
+// Spoken alerts waiting for their turn, more are dropped until the queue drains
+pub const LOG_SPEECH_QUEUE: usize = 5;
+// Matches sent to the window in one log_search_results event
+pub const LOG_SEARCH_BATCH: usize = 100;
+// Milliseconds a partial batch of matches waits before it is sent anyway
+pub const LOG_SEARCH_FLUSH_MILLIS: u64 = 250;
+// A search stops after this many matches unless it asks for another limit
+pub const LOG_SEARCH_MAX_MATCHES: usize = 10_000;
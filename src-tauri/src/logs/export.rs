@@ -0,0 +1,87 @@
+use chrono::{Local, TimeZone};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use super::structs::{ExportFormat, LogLine};
+use crate::scripts::process::now_millis;
+use crate::tts::constants::EXPORT_FOLDER_NAME;
+
+type ExportResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+fn format_timestamp(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|timestamp| Local.timestamp_millis_opt(timestamp).single())
+        .map(|timestamp| timestamp.to_rfc3339())
+        .unwrap_or_default()
+}
+
+// One column per field that appears in any line, after the common ones
+fn write_csv(lines: &[LogLine], path: &Path) -> ExportResult<()> {
+    let field_names = lines
+        .iter()
+        .flat_map(|line| line.fields.keys().cloned())
+        .collect::<BTreeSet<String>>();
+
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut header = vec!["timestamp", "level", "message", "text"];
+    header.extend(field_names.iter().map(|name| name.as_str()));
+    writer.write_record(&header)?;
+
+    for line in lines {
+        let mut record = vec![
+            format_timestamp(line.timestamp),
+            line.level.clone().unwrap_or_default(),
+            line.message.clone().unwrap_or_default(),
+            line.text.clone(),
+        ];
+        record.extend(
+            field_names
+                .iter()
+                .map(|name| line.fields.get(name).cloned().unwrap_or_default()),
+        );
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_json(lines: &[LogLine], path: &Path) -> ExportResult<()> {
+    let records = lines
+        .iter()
+        .map(|line| {
+            serde_json::json!({
+                "timestamp": format_timestamp(line.timestamp),
+                "level": line.level,
+                "message": line.message,
+                "text": line.text,
+                "fields": line.fields
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+
+    serde_json::to_writer_pretty(File::create(path)?, &records)?;
+
+    Ok(())
+}
+
+// Writes the lines the window filtered into the export folder and returns the new file
+pub fn export_lines(lines: &[LogLine], format: ExportFormat) -> ExportResult<PathBuf> {
+    let export_folder = PathBuf::from(EXPORT_FOLDER_NAME);
+    fs::create_dir_all(&export_folder)?;
+
+    let extension = match format {
+        ExportFormat::Csv => "csv",
+        ExportFormat::Json => "json",
+    };
+    let path = export_folder.join(format!("log-{}.{}", now_millis(), extension));
+
+    match format {
+        ExportFormat::Csv => write_csv(lines, &path)?,
+        ExportFormat::Json => write_json(lines, &path)?,
+    }
+    println!("Exported {} log lines to {}", lines.len(), path.display());
+
+    Ok(path)
+}
@@ -0,0 +1,448 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::alerts::raise_alert;
+use super::constants::LOG_RULES_FILE_NAME;
+use super::structs::{LogLine, LogRule, RuleAction};
+use crate::tts::constants::CONFIG_FOLDER_NAME;
+
+type RulesResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+struct CompiledRule {
+    rule: LogRule,
+    regex: Option<Regex>,
+    levels: Vec<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+}
+
+lazy_static! {
+    static ref LEVEL_REGEX: Regex = Regex::new(
+        r"(?i)\b(trace|debug|info|notice|warn|warning|error|err|fatal|critical|crit)\b"
+    )
+    .unwrap();
+    static ref TIMESTAMP_REGEX: Regex = Regex::new(
+        r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?"
+    )
+    .unwrap();
+    // Compiled rules per followed file, replaced when its rules are saved
+    static ref COMPILED: Mutex<HashMap<String, Arc<Vec<CompiledRule>>>> =
+        Mutex::new(HashMap::new());
+    // When an alert rule last fired, keyed by file and rule position
+    static ref LAST_ALERTS: Mutex<HashMap<(String, usize), Instant>> = Mutex::new(HashMap::new());
+}
+
+// Folds the spellings services use into trace, debug, info, warn, error and fatal
+pub fn normalize_level(level: &str) -> String {
+    match level.to_lowercase().as_str() {
+        "warning" => "warn".to_string(),
+        "err" => "error".to_string(),
+        "notice" => "info".to_string(),
+        "critical" | "crit" | "panic" => "fatal".to_string(),
+        other => other.to_string(),
+    }
+}
+
+pub fn detect_level(text: &str) -> Option<String> {
+    LEVEL_REGEX
+        .find(text)
+        .map(|level| normalize_level(level.as_str()))
+}
+
+// Unix milliseconds of an ISO 8601 like timestamp, one without an offset is local time
+pub fn parse_timestamp(text: &str) -> Option<i64> {
+    let mut text = text.trim().replace(',', ".");
+    if text.len() > 10 && text.as_bytes()[10] == b' ' {
+        text.replace_range(10..11, "T");
+    }
+    if let Some(stripped) = text.strip_suffix('Z') {
+        text = format!("{}+00:00", stripped);
+    }
+
+    if let Ok(timestamp) = DateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Some(timestamp.timestamp_millis());
+    }
+
+    NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(|timestamp| timestamp.timestamp_millis())
+}
+
+pub fn detect_timestamp(text: &str) -> Option<i64> {
+    TIMESTAMP_REGEX
+        .find(text)
+        .and_then(|timestamp| parse_timestamp(timestamp.as_str()))
+}
+
+fn get_rules_path() -> PathBuf {
+    let mut path = PathBuf::from(CONFIG_FOLDER_NAME);
+    path.push(LOG_RULES_FILE_NAME);
+    path
+}
+
+// Rule sets keyed by the path of the followed file
+fn load_rule_sets() -> HashMap<String, Vec<LogRule>> {
+    let path = get_rules_path();
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open log rules file: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    match serde_json::from_reader(file) {
+        Ok(rule_sets) => rule_sets,
+        Err(e) => {
+            eprintln!("Failed to parse log rules file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+fn save_rule_sets(rule_sets: &HashMap<String, Vec<LogRule>>) -> RulesResult<()> {
+    let path = get_rules_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(rule_sets)?;
+    fs::write(path, json)?;
+
+    Ok(())
+}
+
+fn compile_rule(rule: &LogRule) -> RulesResult<CompiledRule> {
+    let regex = match rule.pattern.as_ref().filter(|pattern| !pattern.is_empty()) {
+        Some(pattern) => Some(
+            RegexBuilder::new(pattern)
+                .case_insensitive(rule.case_insensitive)
+                .build()
+                .map_err(|e| format!("Invalid log rule pattern: {}", e))?,
+        ),
+        None => None,
+    };
+
+    let parse_bound = |bound: &Option<String>| -> RulesResult<Option<i64>> {
+        match bound.as_ref().filter(|bound| !bound.is_empty()) {
+            Some(bound) => parse_timestamp(bound)
+                .map(Some)
+                .ok_or_else(|| format!("Invalid log rule time: {}", bound).into()),
+            None => Ok(None),
+        }
+    };
+
+    Ok(CompiledRule {
+        rule: rule.clone(),
+        regex,
+        levels: rule
+            .levels
+            .iter()
+            .map(|level| normalize_level(level))
+            .collect(),
+        since: parse_bound(&rule.since)?,
+        until: parse_bound(&rule.until)?,
+    })
+}
+
+impl CompiledRule {
+    fn matches(&self, line: &LogLine) -> bool {
+        if let Some(regex) = &self.regex {
+            let target = match self.rule.field.as_deref().filter(|field| !field.is_empty()) {
+                Some("message") => line.message.as_deref(),
+                Some(field) => line.fields.get(field).map(|value| value.as_str()),
+                None => Some(line.text.as_str()),
+            };
+            if !target.map_or(false, |target| regex.is_match(target)) {
+                return false;
+            }
+        }
+
+        if !self.levels.is_empty()
+            && !line
+                .level
+                .as_ref()
+                .map_or(false, |level| self.levels.contains(level))
+        {
+            return false;
+        }
+
+        if self.since.is_some() || self.until.is_some() {
+            let Some(timestamp) = line.timestamp else {
+                return false;
+            };
+            if self.since.map_or(false, |since| timestamp < since)
+                || self.until.map_or(false, |until| timestamp > until)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+fn compiled_rules(path: &str) -> Arc<Vec<CompiledRule>> {
+    let mut compiled = COMPILED.lock().unwrap();
+    if let Some(rules) = compiled.get(path) {
+        return rules.clone();
+    }
+
+    // Rules that no longer compile are skipped instead of failing the whole file
+    let rules = load_rule_sets()
+        .remove(path)
+        .unwrap_or_default()
+        .iter()
+        .filter(|rule| rule.enabled)
+        .filter_map(|rule| match compile_rule(rule) {
+            Ok(rule) => Some(rule),
+            Err(e) => {
+                eprintln!("Skipping log rule {} of {}: {}", rule.name, path, e);
+                None
+            }
+        })
+        .collect::<Vec<CompiledRule>>();
+    let rules = Arc::new(rules);
+    compiled.insert(path.to_string(), rules.clone());
+
+    rules
+}
+
+pub fn get_rules(path: &str) -> Vec<LogRule> {
+    load_rule_sets().remove(path).unwrap_or_default()
+}
+
+// Saving an empty list removes the file's rule set
+pub fn save_rules(path: &str, rules: Vec<LogRule>) -> RulesResult<()> {
+    let compiled = rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .map(compile_rule)
+        .collect::<RulesResult<Vec<CompiledRule>>>()?;
+
+    let mut rule_sets = load_rule_sets();
+    if rules.is_empty() {
+        rule_sets.remove(path);
+    } else {
+        rule_sets.insert(path.to_string(), rules);
+    }
+    save_rule_sets(&rule_sets)?;
+
+    COMPILED
+        .lock()
+        .unwrap()
+        .insert(path.to_string(), Arc::new(compiled));
+    LAST_ALERTS
+        .lock()
+        .unwrap()
+        .retain(|(alert_path, _), _| alert_path != path);
+
+    Ok(())
+}
+
+fn should_alert(path: &str, index: usize, rule: &LogRule) -> bool {
+    let mut last_alerts = LAST_ALERTS.lock().unwrap();
+    let key = (path.to_string(), index);
+    let cooling_down = last_alerts.get(&key).map_or(false, |fired_at| {
+        fired_at.elapsed() < Duration::from_secs(rule.cooldown_secs)
+    });
+    if !cooling_down {
+        last_alerts.insert(key, Instant::now());
+    }
+
+    !cooling_down
+}
+
+// Alert rules see every line, include and exclude rules then decide what reaches the window
+pub fn apply_rules(path: &str, lines: Vec<LogLine>) -> Vec<LogLine> {
+    let rules = compiled_rules(path);
+    let has_includes = rules
+        .iter()
+        .any(|compiled| compiled.rule.action == RuleAction::Include);
+
+    lines
+        .into_iter()
+        .filter_map(|mut line| {
+            let mut included = !has_includes;
+            let mut excluded = false;
+
+            for (index, compiled) in rules.iter().enumerate() {
+                if !compiled.matches(&line) {
+                    continue;
+                }
+
+                match compiled.rule.action {
+                    RuleAction::Include => included = true,
+                    RuleAction::Exclude => excluded = true,
+                    RuleAction::Highlight => {
+                        if line.highlight.is_none() {
+                            line.highlight = Some(
+                                compiled
+                                    .rule
+                                    .color
+                                    .clone()
+                                    .unwrap_or_else(|| "yellow".to_string()),
+                            );
+                        }
+                    }
+                    RuleAction::Alert => {
+                        if should_alert(path, index, &compiled.rule) {
+                            raise_alert(path, &compiled.rule, &line);
+                        }
+                    }
+                }
+            }
+
+            (included && !excluded).then_some(line)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> LogLine {
+        LogLine {
+            text: text.to_string(),
+            level: detect_level(text),
+            timestamp: detect_timestamp(text),
+            message: None,
+            fields: Default::default(),
+            highlight: None,
+        }
+    }
+
+    // Compiled straight into the cache so the rules file on disk is never read
+    fn with_rules(path: &str, rules: serde_json::Value) {
+        let rules = serde_json::from_value::<Vec<LogRule>>(rules).unwrap();
+        let compiled = rules
+            .iter()
+            .map(|rule| compile_rule(rule).unwrap())
+            .collect();
+        COMPILED
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), Arc::new(compiled));
+    }
+
+    fn texts(lines: Vec<LogLine>) -> Vec<String> {
+        lines.into_iter().map(|line| line.text).collect()
+    }
+
+    #[test]
+    fn timestamps_with_an_offset_or_z_are_utc_based() {
+        assert_eq!(
+            parse_timestamp("2024-05-01T12:00:00Z"),
+            Some(1_714_564_800_000)
+        );
+        assert_eq!(
+            parse_timestamp("2024-05-01 14:00:00,250+02:00"),
+            Some(1_714_564_800_250)
+        );
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn timestamps_without_an_offset_are_local_time() {
+        let local = Local
+            .with_ymd_and_hms(2024, 5, 1, 12, 0, 0)
+            .unwrap()
+            .timestamp_millis();
+
+        assert_eq!(parse_timestamp("2024-05-01 12:00:00"), Some(local));
+        assert_eq!(parse_timestamp("2024-05-01T12:00:00.000"), Some(local));
+    }
+
+    #[test]
+    fn include_rules_hide_lines_that_match_none_of_them() {
+        let path = "tests/include.log";
+        with_rules(
+            path,
+            serde_json::json!([
+                { "action": "include", "levels": ["error"] },
+                { "action": "include", "pattern": "started" }
+            ]),
+        );
+
+        let lines = apply_rules(
+            path,
+            vec![
+                line("ERROR disk full"),
+                line("INFO service started"),
+                line("INFO heartbeat"),
+            ],
+        );
+
+        assert_eq!(texts(lines), ["ERROR disk full", "INFO service started"]);
+    }
+
+    #[test]
+    fn exclude_wins_over_include_and_highlight_keeps_the_first_color() {
+        let path = "tests/exclude.log";
+        with_rules(
+            path,
+            serde_json::json!([
+                { "action": "include", "levels": ["warn", "error"] },
+                { "action": "exclude", "pattern": "retrying", "case_insensitive": true },
+                { "action": "highlight", "pattern": "disk", "color": "red" },
+                { "action": "highlight", "levels": ["error"] }
+            ]),
+        );
+
+        let lines = apply_rules(
+            path,
+            vec![
+                line("WARNING Retrying upload"),
+                line("ERROR disk full"),
+                line("ERR timeout"),
+            ],
+        );
+
+        let highlights = lines
+            .iter()
+            .map(|line| line.highlight.clone())
+            .collect::<Vec<Option<String>>>();
+        assert_eq!(texts(lines), ["ERROR disk full", "ERR timeout"]);
+        assert_eq!(
+            highlights,
+            [Some("red".to_string()), Some("yellow".to_string())]
+        );
+    }
+
+    #[test]
+    fn time_ranges_skip_lines_without_a_timestamp() {
+        let path = "tests/range.log";
+        with_rules(
+            path,
+            serde_json::json!([{
+                "action": "include",
+                "since": "2024-05-01T00:00:00Z",
+                "until": "2024-05-02T00:00:00Z"
+            }]),
+        );
+
+        let lines = apply_rules(
+            path,
+            vec![
+                line("2024-04-30T23:59:59Z before"),
+                line("2024-05-01T08:00:00Z inside"),
+                line("2024-05-02T00:00:01Z after"),
+                line("no timestamp"),
+            ],
+        );
+
+        assert_eq!(texts(lines), ["2024-05-01T08:00:00Z inside"]);
+    }
+}
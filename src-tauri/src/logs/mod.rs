@@ -0,0 +1,8 @@
+pub(crate) mod alerts;
+pub(crate) mod constants;
+pub(crate) mod export;
+pub(crate) mod filters;
+pub(crate) mod parse;
+pub(crate) mod search;
+pub(crate) mod structs;
+pub(crate) mod tail;
@@ -0,0 +1,298 @@
+use chrono::DateTime;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use super::filters::{detect_level, detect_timestamp, normalize_level, parse_timestamp};
+use super::structs::LogLine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    Json,
+    Logfmt,
+    // Apache and nginx common or combined access logs
+    Access,
+    Plain,
+}
+
+// A parsed line before the rules see it
+struct Record {
+    timestamp: Option<i64>,
+    level: Option<String>,
+    message: Option<String>,
+    fields: BTreeMap<String, String>,
+}
+
+const TIMESTAMP_KEYS: [&str; 6] = ["timestamp", "@timestamp", "time", "ts", "datetime", "date"];
+const LEVEL_KEYS: [&str; 5] = ["level", "severity", "lvl", "log.level", "loglevel"];
+const MESSAGE_KEYS: [&str; 4] = ["message", "msg", "@message", "text"];
+// Lines looked at to decide the format of a file
+const DETECT_SAMPLE_LINES: usize = 20;
+
+lazy_static! {
+    static ref ACCESS_REGEX: Regex = Regex::new(
+        r#"^(\S+) (\S+) (\S+) \[([^\]]+)\] "([^"]*)" (\d{3}) (\S+)(?: "([^"]*)" "([^"]*)")?"#
+    )
+    .unwrap();
+    static ref LOGFMT_REGEX: Regex = Regex::new(r#"([\w.@-]+)=("(?:[^"\\]|\\.)*"|\S*)"#).unwrap();
+}
+
+fn parse_json(text: &str) -> Option<BTreeMap<String, String>> {
+    let serde_json::Value::Object(object) =
+        serde_json::from_str::<serde_json::Value>(text.trim()).ok()?
+    else {
+        return None;
+    };
+
+    Some(
+        object
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(value) => value,
+                    other => other.to_string(),
+                };
+                (key, value)
+            })
+            .collect(),
+    )
+}
+
+// key=value pairs, a line needs at least two of them to count as logfmt
+fn parse_logfmt(text: &str) -> Option<BTreeMap<String, String>> {
+    let fields = LOGFMT_REGEX
+        .captures_iter(text)
+        .map(|captures| {
+            let value = &captures[2];
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .map(|value| value.replace("\\\"", "\"").replace("\\\\", "\\"))
+                .unwrap_or_else(|| value.to_string());
+            (captures[1].to_string(), value)
+        })
+        .collect::<BTreeMap<String, String>>();
+
+    (fields.len() >= 2).then_some(fields)
+}
+
+fn parse_access(text: &str) -> Option<Record> {
+    let captures = ACCESS_REGEX.captures(text)?;
+    let request = captures[5].to_string();
+    let status = captures[6].to_string();
+
+    let mut fields = BTreeMap::new();
+    fields.insert("remote_addr".to_string(), captures[1].to_string());
+    fields.insert("user".to_string(), captures[3].to_string());
+    let mut request_parts = request.split(' ');
+    if let (Some(method), Some(path)) = (request_parts.next(), request_parts.next()) {
+        fields.insert("method".to_string(), method.to_string());
+        fields.insert("path".to_string(), path.to_string());
+    }
+    fields.insert("status".to_string(), status.clone());
+    fields.insert("bytes".to_string(), captures[7].to_string());
+    if let Some(referer) = captures.get(8) {
+        fields.insert("referer".to_string(), referer.as_str().to_string());
+    }
+    if let Some(user_agent) = captures.get(9) {
+        fields.insert("user_agent".to_string(), user_agent.as_str().to_string());
+    }
+
+    let level = match status.chars().next() {
+        Some('5') => "error",
+        Some('4') => "warn",
+        _ => "info",
+    };
+
+    Some(Record {
+        timestamp: DateTime::parse_from_str(&captures[4], "%d/%b/%Y:%H:%M:%S %z")
+            .ok()
+            .map(|timestamp| timestamp.timestamp_millis()),
+        level: Some(level.to_string()),
+        message: Some(request),
+        fields,
+    })
+}
+
+// Numbers are Unix seconds, or milliseconds once they are too large for seconds
+fn field_timestamp(value: &str) -> Option<i64> {
+    match value.parse::<f64>() {
+        Ok(number) if number > 1e11 => Some(number as i64),
+        Ok(number) => Some((number * 1000.0) as i64),
+        Err(_) => parse_timestamp(value),
+    }
+}
+
+// Takes the well known keys out of the fields, the rest stay as they are
+fn from_fields(mut fields: BTreeMap<String, String>) -> Record {
+    let mut take = |keys: &[&str]| keys.iter().find_map(|key| fields.remove(*key));
+
+    let timestamp = take(&TIMESTAMP_KEYS).and_then(|value| field_timestamp(&value));
+    let level = take(&LEVEL_KEYS).map(|level| normalize_level(&level));
+    let message = take(&MESSAGE_KEYS);
+
+    Record {
+        timestamp,
+        level,
+        message,
+        fields,
+    }
+}
+
+fn parse_record(text: &str, format: LogFormat) -> Option<Record> {
+    match format {
+        LogFormat::Json => parse_json(text).map(from_fields),
+        LogFormat::Logfmt => parse_logfmt(text).map(from_fields),
+        LogFormat::Access => parse_access(text),
+        LogFormat::Plain => None,
+    }
+}
+
+// The format most of the sample parses as, plain text when nothing fits
+pub fn detect_format(lines: &[String]) -> LogFormat {
+    let sample = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .take(DETECT_SAMPLE_LINES)
+        .collect::<Vec<&String>>();
+    if sample.is_empty() {
+        return LogFormat::Plain;
+    }
+
+    [LogFormat::Json, LogFormat::Access, LogFormat::Logfmt]
+        .into_iter()
+        .map(|format| {
+            let parsed = sample
+                .iter()
+                .filter(|line| parse_record(line, format).is_some())
+                .count();
+            (format, parsed)
+        })
+        .filter(|(_, parsed)| *parsed * 2 > sample.len())
+        // The first format wins a tie
+        .min_by_key(|(_, parsed)| Reverse(*parsed))
+        .map_or(LogFormat::Plain, |(format, _)| format)
+}
+
+// Lines that do not fit the format, like stack traces between JSON lines, fall back to plain text
+pub fn parse_line(text: String, format: LogFormat) -> LogLine {
+    match parse_record(&text, format) {
+        Some(record) => LogLine {
+            timestamp: record.timestamp,
+            level: record.level,
+            message: record.message,
+            fields: record.fields,
+            text,
+            highlight: None,
+        },
+        None => LogLine {
+            timestamp: detect_timestamp(&text),
+            level: detect_level(&text),
+            message: None,
+            fields: BTreeMap::new(),
+            text,
+            highlight: None,
+        },
+    }
+}
+
+pub fn parse_lines(lines: Vec<String>, format: LogFormat) -> Vec<LogLine> {
+    lines
+        .into_iter()
+        .map(|text| parse_line(text, format))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn detects_each_format_and_falls_back_to_plain() {
+        let json = sample(&[
+            r#"{"level":"info","msg":"started"}"#,
+            r#"{"level":"error","msg":"failed"}"#,
+        ]);
+        let logfmt = sample(&[
+            r#"level=info msg="started" port=8080"#,
+            r#"level=warn msg="slow request" took=2s"#,
+        ]);
+        let access = sample(&[
+            r#"127.0.0.1 - - [01/May/2024:12:00:00 +0000] "GET / HTTP/1.1" 200 512"#,
+            r#"127.0.0.1 - bob [01/May/2024:12:00:01 +0000] "POST /login HTTP/1.1" 401 0"#,
+        ]);
+        let plain = sample(&["service started", "", "listening on port 8080"]);
+
+        assert_eq!(detect_format(&json), LogFormat::Json);
+        assert_eq!(detect_format(&logfmt), LogFormat::Logfmt);
+        assert_eq!(detect_format(&access), LogFormat::Access);
+        assert_eq!(detect_format(&plain), LogFormat::Plain);
+        assert_eq!(detect_format(&[]), LogFormat::Plain);
+    }
+
+    #[test]
+    fn logfmt_values_can_be_quoted_and_escaped() {
+        let fields =
+            parse_logfmt(r#"level=info msg="said \"hi\" to C:\\temp" empty= path=/var/log"#)
+                .unwrap();
+
+        assert_eq!(fields["msg"], r#"said "hi" to C:\temp"#);
+        assert_eq!(fields["empty"], "");
+        assert_eq!(fields["path"], "/var/log");
+        assert!(parse_logfmt("just one=pair").is_none());
+    }
+
+    #[test]
+    fn access_lines_are_split_into_fields() {
+        let record = parse_access(
+            r#"10.0.0.1 - alice [01/May/2024:14:00:00 +0200] "GET /api?q=1 HTTP/1.1" 503 12 "https://example.com/" "curl/8.0""#,
+        )
+        .unwrap();
+
+        assert_eq!(record.timestamp, Some(1_714_564_800_000));
+        assert_eq!(record.level.as_deref(), Some("error"));
+        assert_eq!(record.message.as_deref(), Some("GET /api?q=1 HTTP/1.1"));
+        assert_eq!(record.fields["remote_addr"], "10.0.0.1");
+        assert_eq!(record.fields["user"], "alice");
+        assert_eq!(record.fields["method"], "GET");
+        assert_eq!(record.fields["path"], "/api?q=1");
+        assert_eq!(record.fields["status"], "503");
+        assert_eq!(record.fields["referer"], "https://example.com/");
+        assert_eq!(record.fields["user_agent"], "curl/8.0");
+    }
+
+    #[test]
+    fn numeric_timestamps_switch_to_milliseconds_above_1e11() {
+        assert_eq!(field_timestamp("1714564800"), Some(1_714_564_800_000));
+        assert_eq!(field_timestamp("1714564800.5"), Some(1_714_564_800_500));
+        assert_eq!(field_timestamp("1714564800000"), Some(1_714_564_800_000));
+        assert_eq!(
+            field_timestamp("2024-05-01T12:00:00Z"),
+            Some(1_714_564_800_000)
+        );
+        assert_eq!(field_timestamp("soon"), None);
+    }
+
+    #[test]
+    fn known_keys_are_taken_out_and_unparsed_lines_stay_plain() {
+        let line = parse_line(
+            r#"{"ts":1714564800,"severity":"WARNING","message":"slow","id":7}"#.to_string(),
+            LogFormat::Json,
+        );
+        assert_eq!(line.timestamp, Some(1_714_564_800_000));
+        assert_eq!(line.level.as_deref(), Some("warn"));
+        assert_eq!(line.message.as_deref(), Some("slow"));
+        assert_eq!(line.fields.keys().collect::<Vec<&String>>(), ["id"]);
+
+        let trace = parse_line("    at main (app.js:1) ERROR".to_string(), LogFormat::Json);
+        assert_eq!(trace.level.as_deref(), Some("error"));
+        assert!(trace.message.is_none() && trace.fields.is_empty());
+    }
+}
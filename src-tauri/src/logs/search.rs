@@ -0,0 +1,356 @@
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::constants::{LOG_SEARCH_BATCH, LOG_SEARCH_FLUSH_MILLIS, LOG_SEARCH_MAX_MATCHES};
+use super::structs::{LogSearch, SearchMatch};
+use super::tail::{decode, detect_bom, line_end, Encoding};
+use crate::scripts::events::emit;
+use crate::scripts::process::now_millis;
+
+type SearchResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+lazy_static! {
+    // Cancel flags of the searches that are still running, keyed by search id
+    static ref SEARCHES: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+}
+
+static SEARCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn next_search_id() -> String {
+    let counter = SEARCH_COUNTER.fetch_add(1, Ordering::SeqCst);
+    format!("{:x}-{}", now_millis(), counter)
+}
+
+// Rotated siblings like app.log.1 or app.log.3.gz come first, highest number first,
+// so matches arrive from the oldest lines to the newest
+fn search_files(path: &Path, include_rotated: bool) -> Vec<PathBuf> {
+    let mut files = vec![];
+
+    if let (true, Some(name)) = (include_rotated, path.file_name()) {
+        let prefix = format!("{}.", name.to_string_lossy());
+        let folder = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut rotated = fs::read_dir(folder)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let suffix = file_name.strip_prefix(&prefix)?;
+                let number = suffix
+                    .strip_suffix(".gz")
+                    .unwrap_or(suffix)
+                    .parse::<u32>()
+                    .ok()?;
+                Some((number, entry.path()))
+            })
+            .collect::<Vec<(u32, PathBuf)>>();
+        rotated.sort_by(|a, b| b.0.cmp(&a.0));
+        files.extend(rotated.into_iter().map(|(_, path)| path));
+    }
+
+    files.push(path.to_path_buf());
+    files
+}
+
+// Read line by line, a compressed file is decompressed while it is read
+fn open_reader(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    if path
+        .extension()
+        .map_or(false, |extension| extension == "gz")
+    {
+        return Ok(Box::new(BufReader::new(GzDecoder::new(file))));
+    }
+
+    Ok(Box::new(BufReader::new(file)))
+}
+
+// Splits and decodes lines the way the tailer does, so UTF-16 logs are searchable and
+// a file without newlines never has to fit in memory
+struct LineReader {
+    reader: Box<dyn BufRead>,
+    encoding: Encoding,
+    pending: Vec<u8>,
+    chunk: Vec<u8>,
+}
+
+impl LineReader {
+    fn open(path: &Path) -> io::Result<Self> {
+        let mut reader = open_reader(path)?;
+        let (encoding, bom_length) = detect_bom(reader.fill_buf()?);
+        reader.consume(bom_length as usize);
+
+        Ok(LineReader {
+            reader,
+            encoding,
+            pending: vec![],
+            chunk: vec![0u8; 8192],
+        })
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if let Some(end) = line_end(&self.pending, self.encoding) {
+                let bytes = self.pending.drain(..end).collect::<Vec<u8>>();
+                return Ok(Some(decode(&bytes, self.encoding)));
+            }
+
+            let read = self.reader.read(&mut self.chunk)?;
+            if read == 0 {
+                // The last line may end without a newline
+                if self.pending.is_empty() {
+                    return Ok(None);
+                }
+                let bytes = std::mem::take(&mut self.pending);
+                return Ok(Some(decode(&bytes, self.encoding)));
+            }
+            self.pending.extend_from_slice(&self.chunk[..read]);
+        }
+    }
+}
+
+fn emit_status(search_id: &str, status: &str, details: serde_json::Value) {
+    let mut payload = serde_json::json!({
+        "search_id": search_id,
+        "status": status
+    });
+    if let (Some(payload), serde_json::Value::Object(details)) = (payload.as_object_mut(), details)
+    {
+        payload.extend(details);
+    }
+
+    emit("log_search_status", payload);
+}
+
+// Collects matches and sends them in batches, or sooner when matches are rare
+struct Batch {
+    search_id: String,
+    matches: Vec<SearchMatch>,
+    last_flush: Instant,
+    total: usize,
+}
+
+impl Batch {
+    fn push(&mut self, search_match: SearchMatch) {
+        self.matches.push(search_match);
+        self.total += 1;
+
+        if self.matches.len() >= LOG_SEARCH_BATCH
+            || self.last_flush.elapsed() >= Duration::from_millis(LOG_SEARCH_FLUSH_MILLIS)
+        {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        if self.matches.is_empty() {
+            return;
+        }
+
+        emit(
+            "log_search_results",
+            serde_json::json!({
+                "search_id": self.search_id,
+                "matches": std::mem::take(&mut self.matches)
+            }),
+        );
+    }
+}
+
+// A match that still waits for the lines after it
+struct PendingMatch {
+    search_match: SearchMatch,
+    missing: usize,
+}
+
+// Returns false once the search should end, because it was cancelled or found enough
+fn search_file(
+    file: &Path,
+    regex: &Regex,
+    search: &LogSearch,
+    cancelled: &AtomicBool,
+    batch: &mut Batch,
+    limit: usize,
+) -> io::Result<bool> {
+    let mut reader = LineReader::open(file)?;
+    let file_name = file.to_string_lossy().to_string();
+    let mut before: VecDeque<String> = VecDeque::with_capacity(search.context);
+    let mut pending: VecDeque<PendingMatch> = VecDeque::new();
+    let mut found = batch.total;
+    let mut line_number = 0;
+
+    while !cancelled.load(Ordering::SeqCst) {
+        let Some(line) = reader.next_line()? else {
+            // The end of the file cuts the context of the last matches short
+            pending
+                .drain(..)
+                .for_each(|waiting| batch.push(waiting.search_match));
+            return Ok(true);
+        };
+        line_number += 1;
+
+        for waiting in pending.iter_mut() {
+            waiting.search_match.after.push(line.clone());
+            waiting.missing -= 1;
+        }
+        while pending
+            .front()
+            .map_or(false, |waiting| waiting.missing == 0)
+        {
+            if let Some(waiting) = pending.pop_front() {
+                batch.push(waiting.search_match);
+            }
+        }
+
+        if found >= limit {
+            if pending.is_empty() {
+                return Ok(false);
+            }
+        } else if regex.is_match(&line) {
+            found += 1;
+            let search_match = SearchMatch {
+                file: file_name.clone(),
+                line_number,
+                line: line.clone(),
+                before: before.iter().cloned().collect(),
+                after: vec![],
+            };
+
+            if search.context == 0 {
+                batch.push(search_match);
+            } else {
+                pending.push_back(PendingMatch {
+                    search_match,
+                    missing: search.context,
+                });
+            }
+        }
+
+        if search.context > 0 {
+            if before.len() == search.context {
+                before.pop_front();
+            }
+            before.push_back(line);
+        }
+    }
+
+    Ok(false)
+}
+
+fn run_search(
+    search_id: String,
+    search: LogSearch,
+    regex: Regex,
+    files: Vec<PathBuf>,
+    cancelled: Arc<AtomicBool>,
+) {
+    let limit = search.max_matches.unwrap_or(LOG_SEARCH_MAX_MATCHES);
+    let mut batch = Batch {
+        search_id: search_id.clone(),
+        matches: vec![],
+        last_flush: Instant::now(),
+        total: 0,
+    };
+    let mut status = "done";
+
+    for file in files {
+        emit_status(
+            &search_id,
+            "searching",
+            serde_json::json!({ "file": file.to_string_lossy() }),
+        );
+
+        match search_file(&file, &regex, &search, &cancelled, &mut batch, limit) {
+            Ok(true) => {}
+            Ok(false) => {
+                status = if cancelled.load(Ordering::SeqCst) {
+                    "cancelled"
+                } else {
+                    "limit_reached"
+                };
+                break;
+            }
+            // A rotated file that went away or is not valid gzip does not end the search
+            Err(e) => emit_status(
+                &search_id,
+                "file_error",
+                serde_json::json!({
+                    "file": file.to_string_lossy(),
+                    "message": e.to_string()
+                }),
+            ),
+        }
+    }
+
+    batch.flush();
+    println!(
+        "Log Search - {} in {} {} with {} matches",
+        search.query, search.path, status, batch.total
+    );
+    emit_status(
+        &search_id,
+        status,
+        serde_json::json!({ "matches": batch.total }),
+    );
+
+    SEARCHES.lock().unwrap().remove(&search_id);
+}
+
+// Starts the search in the background and returns its id, results arrive as events
+pub fn start_search(search: LogSearch) -> SearchResult<String> {
+    if search.query.is_empty() {
+        return Err("Search query is empty".into());
+    }
+
+    let pattern = if search.regex {
+        search.query.clone()
+    } else {
+        regex::escape(&search.query)
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(search.case_insensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))?;
+
+    let path = Path::new(&search.path);
+    if !path.exists() {
+        return Err(format!("Log file {} does not exist", search.path).into());
+    }
+    let files = search_files(path, search.include_rotated);
+
+    let search_id = next_search_id();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    SEARCHES
+        .lock()
+        .unwrap()
+        .insert(search_id.clone(), cancelled.clone());
+
+    let thread_search_id = search_id.clone();
+    thread::spawn(move || run_search(thread_search_id, search, regex, files, cancelled));
+
+    Ok(search_id)
+}
+
+pub fn cancel_search(search_id: &str) -> bool {
+    match SEARCHES.lock().unwrap().get(search_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    // Once a file has include rules only lines matching one of them are shown
+    Include,
+    Exclude,
+    Highlight,
+    Alert,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertTarget {
+    Notification,
+    // Read out with the current voice
+    Speak,
+    // Receives the path, rule name and line as JSON in a POST request
+    Webhook { url: String },
+}
+
+// Every criterion that is set has to match, a rule without any matches every line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRule {
+    #[serde(default)]
+    pub(crate) name: String,
+    pub(crate) action: RuleAction,
+    // Regular expression searched anywhere in the line
+    #[serde(default)]
+    pub(crate) pattern: Option<String>,
+    // Searches the pattern in this parsed field instead, "message" is the parsed message
+    #[serde(default)]
+    pub(crate) field: Option<String>,
+    #[serde(default)]
+    pub(crate) case_insensitive: bool,
+    // Level keywords like "error" or "warn", compared case-insensitively
+    #[serde(default)]
+    pub(crate) levels: Vec<String>,
+    // RFC 3339 or "YYYY-MM-DD HH:MM:SS" in local time, lines without a timestamp never match
+    #[serde(default)]
+    pub(crate) since: Option<String>,
+    #[serde(default)]
+    pub(crate) until: Option<String>,
+    // CSS color of highlighted lines
+    #[serde(default)]
+    pub(crate) color: Option<String>,
+    #[serde(default)]
+    pub(crate) alerts: Vec<AlertTarget>,
+    // Seconds an alert rule stays quiet after it fired
+    #[serde(default = "default_cooldown_secs")]
+    pub(crate) cooldown_secs: u64,
+    #[serde(default = "default_enabled")]
+    pub(crate) enabled: bool,
+}
+
+fn default_cooldown_secs() -> u64 {
+    30
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// A line as it is sent to the window, the window hands them back for exports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogLine {
+    pub(crate) text: String,
+    #[serde(default)]
+    pub(crate) level: Option<String>,
+    // Unix timestamp in milliseconds
+    #[serde(default)]
+    pub(crate) timestamp: Option<i64>,
+    #[serde(default)]
+    pub(crate) message: Option<String>,
+    // Everything else a structured line carried, values of nested JSON stay JSON text
+    #[serde(default)]
+    pub(crate) fields: BTreeMap<String, String>,
+    #[serde(default)]
+    pub(crate) highlight: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSearch {
+    pub(crate) path: String,
+    pub(crate) query: String,
+    // The query is a regular expression instead of plain text
+    #[serde(default)]
+    pub(crate) regex: bool,
+    #[serde(default)]
+    pub(crate) case_insensitive: bool,
+    // Lines sent before and after every match
+    #[serde(default = "default_context_lines")]
+    pub(crate) context: usize,
+    // Also searches rotated files like app.log.1 and app.log.2.gz, oldest first
+    #[serde(default = "default_enabled")]
+    pub(crate) include_rotated: bool,
+    #[serde(default)]
+    pub(crate) max_matches: Option<usize>,
+}
+
+fn default_context_lines() -> usize {
+    2
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    pub(crate) file: String,
+    // Counted from 1 in each file
+    pub(crate) line_number: usize,
+    pub(crate) line: String,
+    pub(crate) before: Vec<String>,
+    pub(crate) after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
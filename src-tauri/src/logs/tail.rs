@@ -0,0 +1,588 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::constants::{
+    LOG_ACK_TIMEOUT_SECS, LOG_BATCH_LINES, LOG_INITIAL_BYTES, LOG_MAX_LINE_BYTES,
+    LOG_MAX_UNACKED_BATCHES, LOG_TAIL_TICK_MILLIS,
+};
+use super::filters::apply_rules;
+use super::parse::{detect_format, parse_lines, LogFormat};
+use super::structs::LogLine;
+use crate::scripts::events::emit;
+
+// Shared between a follower thread and the commands that stop and acknowledge it
+struct TailControl {
+    stop: AtomicBool,
+    // Batches sent to the window that it has not acknowledged yet
+    in_flight: AtomicUsize,
+    last_ack: Mutex<Instant>,
+}
+
+impl TailControl {
+    // Reading pauses while the window is behind, the unread lines simply wait in the file
+    fn is_throttled(&self) -> bool {
+        if self.in_flight.load(Ordering::SeqCst) < LOG_MAX_UNACKED_BATCHES {
+            return false;
+        }
+
+        // A window that was reloaded never acknowledges, start over instead of stalling forever
+        let mut last_ack = self.last_ack.lock().unwrap();
+        if last_ack.elapsed() >= Duration::from_secs(LOG_ACK_TIMEOUT_SECS) {
+            self.in_flight.store(0, Ordering::SeqCst);
+            *last_ack = Instant::now();
+            return false;
+        }
+
+        true
+    }
+}
+
+lazy_static! {
+    // Followed files keyed by the path the window asked for
+    static ref TAILS: Mutex<HashMap<String, Arc<TailControl>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileChange {
+    Truncated,
+    Rotated,
+    Missing,
+}
+
+impl FileChange {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FileChange::Truncated => "truncated",
+            FileChange::Rotated => "rotated",
+            FileChange::Missing => "missing",
+        }
+    }
+}
+
+// Identifies the file behind a path, so a rotated log is noticed even when the new file is larger
+#[cfg(unix)]
+fn file_identity(file: &File) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = file.metadata().ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+// The volume serial number and file index, a creation time can be carried over to a new
+// file created under the old name (NTFS tunneling)
+#[cfg(windows)]
+fn file_identity(file: &File) -> Option<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::fileapi::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+        return None;
+    }
+
+    Some((
+        info.dwVolumeSerialNumber as u64,
+        ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64,
+    ))
+}
+
+// The encoding the first bytes of a file announce and the length of the byte order mark
+pub(crate) fn detect_bom(start: &[u8]) -> (Encoding, u64) {
+    match start {
+        [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+        [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+        [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+        _ => (Encoding::Utf8, 0),
+    }
+}
+
+fn detect_encoding(file: &mut File) -> io::Result<(Encoding, u64)> {
+    let mut bom = [0u8; 3];
+    file.seek(SeekFrom::Start(0))?;
+    let read = file.read(&mut bom)?;
+
+    Ok(detect_bom(&bom[..read]))
+}
+
+// Decodes lossily so a stray byte never stops the tail
+pub(crate) fn decode(bytes: &[u8], encoding: Encoding) -> String {
+    let text = match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = bytes
+                .chunks_exact(2)
+                .map(|unit| match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes([unit[0], unit[1]]),
+                    _ => u16::from_be_bytes([unit[0], unit[1]]),
+                })
+                .collect::<Vec<u16>>();
+            String::from_utf16_lossy(&units)
+        }
+    };
+
+    text.trim_end_matches(['\r', '\n']).to_string()
+}
+
+// End of the next complete line in `pending`, or of an overlong one
+pub(crate) fn line_end(pending: &[u8], encoding: Encoding) -> Option<usize> {
+    let end = match encoding {
+        Encoding::Utf8 => pending
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map(|index| index + 1),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let newline = if encoding == Encoding::Utf16Le {
+                [b'\n', 0]
+            } else {
+                [0, b'\n']
+            };
+            pending
+                .chunks_exact(2)
+                .position(|unit| unit == newline)
+                .map(|index| index * 2 + 2)
+        }
+    };
+
+    end.or_else(|| (pending.len() >= LOG_MAX_LINE_BYTES).then(|| pending.len() - pending.len() % 2))
+}
+
+// Reads one file from a remembered position, keeping the bytes of an unfinished line
+struct Follower {
+    path: PathBuf,
+    file: Option<File>,
+    identity: Option<(u64, u64)>,
+    position: u64,
+    start: u64,
+    encoding: Encoding,
+    pending: Vec<u8>,
+    // Set when reading started in the middle of the file, the first line is cut off
+    skip_partial: bool,
+}
+
+impl Follower {
+    fn new(path: PathBuf) -> Self {
+        Follower {
+            path,
+            file: None,
+            identity: None,
+            position: 0,
+            start: 0,
+            encoding: Encoding::Utf8,
+            pending: vec![],
+            skip_partial: false,
+        }
+    }
+
+    fn open(&mut self, from_start: bool) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        let metadata = file.metadata()?;
+        let (encoding, bom_length) = detect_encoding(&mut file)?;
+
+        let mut position = if from_start {
+            bom_length
+        } else {
+            metadata
+                .len()
+                .saturating_sub(LOG_INITIAL_BYTES)
+                .max(bom_length)
+        };
+        if encoding != Encoding::Utf8 {
+            // Stay on a UTF-16 code unit boundary
+            position -= (position - bom_length) % 2;
+        }
+
+        self.identity = file_identity(&file);
+        self.file = Some(file);
+        self.encoding = encoding;
+        self.start = bom_length;
+        self.position = position;
+        self.skip_partial = position > bom_length;
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.file = None;
+        self.pending.clear();
+    }
+
+    fn rewind(&mut self) {
+        self.position = self.start;
+        self.skip_partial = false;
+        self.pending.clear();
+    }
+
+    fn detect_change(&self) -> Option<FileChange> {
+        // Opened instead of stat'ed, Windows only hands out the file index through a handle
+        let (file, metadata) = match File::open(&self.path)
+            .and_then(|file| file.metadata().map(|metadata| (file, metadata)))
+        {
+            Ok(opened) => opened,
+            Err(_) => return Some(FileChange::Missing),
+        };
+
+        if file_identity(&file) != self.identity {
+            return Some(FileChange::Rotated);
+        }
+        if metadata.len() < self.position {
+            return Some(FileChange::Truncated);
+        }
+
+        None
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        loop {
+            let end = line_end(&self.pending, self.encoding)?;
+            let bytes = self.pending.drain(..end).collect::<Vec<u8>>();
+
+            if self.skip_partial {
+                self.skip_partial = false;
+                continue;
+            }
+
+            return Some(decode(&bytes, self.encoding));
+        }
+    }
+
+    // Whatever is left of a file that is about to be closed, even without a newline
+    fn take_rest(&mut self) -> Option<String> {
+        if self.pending.is_empty() || self.skip_partial {
+            self.pending.clear();
+            return None;
+        }
+
+        let bytes = std::mem::take(&mut self.pending);
+        Some(decode(&bytes, self.encoding))
+    }
+
+    fn read_lines(&mut self, max_lines: usize) -> io::Result<Vec<String>> {
+        let mut lines = vec![];
+        let Some(file) = self.file.as_mut() else {
+            return Ok(lines);
+        };
+        file.seek(SeekFrom::Start(self.position))?;
+
+        let mut chunk = vec![0u8; 8192];
+        while lines.len() < max_lines {
+            if let Some(line) = self.take_line() {
+                lines.push(line);
+                continue;
+            }
+
+            let read = self
+                .file
+                .as_mut()
+                .map_or(Ok(0), |file| file.read(&mut chunk))?;
+            if read == 0 {
+                break;
+            }
+            self.position += read as u64;
+            self.pending.extend_from_slice(&chunk[..read]);
+        }
+
+        Ok(lines)
+    }
+}
+
+fn emit_status(path: &str, status: &str, message: Option<String>) {
+    emit(
+        "log_tail_status",
+        serde_json::json!({
+            "path": path,
+            "status": status,
+            "message": message
+        }),
+    );
+}
+
+fn send_lines(path: &str, format: LogFormat, lines: Vec<LogLine>, control: &TailControl) {
+    control.in_flight.fetch_add(1, Ordering::SeqCst);
+    emit(
+        "log_lines",
+        serde_json::json!({
+            "path": path,
+            "format": format,
+            "lines": lines
+        }),
+    );
+}
+
+fn follow(path: String, control: Arc<TailControl>) {
+    let tick = Duration::from_millis(LOG_TAIL_TICK_MILLIS);
+    let mut follower = Follower::new(PathBuf::from(&path));
+    // The first open starts near the end, files that appear later are read from the top
+    let mut from_start = false;
+    let mut reported_open_error = false;
+    // Detected from the first lines of every file the path points to
+    let mut format: Option<LogFormat> = None;
+
+    while !control.stop.load(Ordering::SeqCst) {
+        if control.is_throttled() {
+            thread::sleep(tick);
+            continue;
+        }
+
+        if follower.file.is_none() {
+            if let Err(e) = follower.open(from_start) {
+                if !reported_open_error {
+                    let status = if e.kind() == io::ErrorKind::NotFound {
+                        "missing"
+                    } else {
+                        "error"
+                    };
+                    emit_status(&path, status, Some(e.to_string()));
+                    reported_open_error = true;
+                }
+                from_start = true;
+                thread::sleep(tick);
+                continue;
+            }
+
+            if reported_open_error {
+                emit_status(&path, "reopened", None);
+            }
+            reported_open_error = false;
+        }
+
+        // Checked before reading, lines written just before a rotation still come from the old handle
+        let change = follower.detect_change();
+        let mut lines = match follower.read_lines(LOG_BATCH_LINES) {
+            Ok(lines) => lines,
+            Err(e) => {
+                emit_status(&path, "error", Some(e.to_string()));
+                follower.close();
+                thread::sleep(tick);
+                continue;
+            }
+        };
+        let batch_full = lines.len() >= LOG_BATCH_LINES;
+
+        let line_format = match format {
+            Some(format) => format,
+            None if lines.is_empty() => LogFormat::Plain,
+            None => *format.insert(detect_format(&lines)),
+        };
+
+        if let Some(change) = change.filter(|_| !batch_full) {
+            lines.extend(follower.take_rest());
+            format = None;
+            match change {
+                FileChange::Truncated => follower.rewind(),
+                FileChange::Rotated | FileChange::Missing => {
+                    follower.close();
+                    from_start = true;
+                    // Reported below, the failing reopen should not report it again
+                    reported_open_error = change == FileChange::Missing;
+                }
+            }
+        }
+
+        // Filtered here so alert rules also see the lines the window never shows
+        let lines = apply_rules(&path, parse_lines(lines, line_format));
+        if !lines.is_empty() {
+            send_lines(&path, line_format, lines, &control);
+        }
+        if let Some(change) = change.filter(|_| !batch_full) {
+            println!("Log Tail - {} was {}", path, change.as_str());
+            emit_status(&path, change.as_str(), None);
+        }
+
+        // A full batch means there is more to read right away
+        if !batch_full {
+            thread::sleep(tick);
+        }
+    }
+}
+
+// Follows the file until stopped, a missing file is waited for
+pub fn start_tail(path: &str) -> io::Result<()> {
+    if path.trim().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Log file path is empty",
+        ));
+    }
+
+    let mut tails = TAILS.lock().unwrap();
+    if tails.contains_key(path) {
+        return Ok(());
+    }
+
+    let control = Arc::new(TailControl {
+        stop: AtomicBool::new(false),
+        in_flight: AtomicUsize::new(0),
+        last_ack: Mutex::new(Instant::now()),
+    });
+    tails.insert(path.to_string(), control.clone());
+
+    let thread_path = path.to_string();
+    thread::spawn(move || follow(thread_path, control));
+
+    println!("Log Tail - following {}", path);
+    emit_status(path, "started", None);
+
+    Ok(())
+}
+
+pub fn stop_tail(path: &str) -> bool {
+    let Some(control) = TAILS.lock().unwrap().remove(path) else {
+        return false;
+    };
+    control.stop.store(true, Ordering::SeqCst);
+
+    println!("Log Tail - stopped {}", path);
+    emit_status(path, "stopped", None);
+
+    true
+}
+
+pub fn list_tails() -> Vec<String> {
+    let mut paths = TAILS
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<String>>();
+    paths.sort();
+
+    paths
+}
+
+// The window calls this after it rendered a log_lines batch
+pub fn ack_lines(path: &str) {
+    if let Some(control) = TAILS.lock().unwrap().get(path) {
+        let _ = control
+            .in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                Some(count.saturating_sub(1))
+            });
+        *control.last_ack.lock().unwrap() = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn utf16(text: &str, encoding: Encoding) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| match encoding {
+                Encoding::Utf16Be => unit.to_be_bytes(),
+                _ => unit.to_le_bytes(),
+            })
+            .collect()
+    }
+
+    // A file in the temp folder that is removed again when the test ends
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "win-tools-tail-{}-{}.log",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, contents).unwrap();
+            TempLog(path)
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+            let _ = fs::remove_file(self.0.with_extension("log.1"));
+        }
+    }
+
+    #[test]
+    fn utf16_lines_split_on_whole_code_units() {
+        for encoding in [Encoding::Utf16Le, Encoding::Utf16Be] {
+            let bytes = utf16("first\r\nzweite Zeile\n", encoding);
+
+            let end = line_end(&bytes, encoding).unwrap();
+            assert_eq!(decode(&bytes[..end], encoding), "first");
+            assert_eq!(decode(&bytes[end..], encoding), "zweite Zeile");
+        }
+
+        // A \n byte inside another character is not a newline
+        let bytes = utf16("\u{0A00}x", Encoding::Utf16Be);
+        assert_eq!(line_end(&bytes, Encoding::Utf16Be), None);
+    }
+
+    #[test]
+    fn overlong_lines_are_cut_at_the_cap_on_an_even_byte() {
+        let bytes = vec![b'x'; LOG_MAX_LINE_BYTES + 1];
+
+        assert_eq!(line_end(&bytes[..100], Encoding::Utf8), None);
+        let end = line_end(&bytes, Encoding::Utf16Le).unwrap();
+        assert_eq!(end, LOG_MAX_LINE_BYTES);
+        assert_eq!(end % 2, 0);
+    }
+
+    #[test]
+    fn byte_order_marks_are_detected_and_stripped() {
+        assert_eq!(detect_bom(&[0xFF, 0xFE, b'a']), (Encoding::Utf16Le, 2));
+        assert_eq!(detect_bom(&[0xFE, 0xFF]), (Encoding::Utf16Be, 2));
+        assert_eq!(detect_bom(&[0xEF, 0xBB, 0xBF, b'a']), (Encoding::Utf8, 3));
+        assert_eq!(detect_bom(b"plain"), (Encoding::Utf8, 0));
+        assert_eq!(detect_bom(&[]), (Encoding::Utf8, 0));
+
+        let mut contents = vec![0xFF, 0xFE];
+        contents.extend(utf16("first\nsecond\n", Encoding::Utf16Le));
+        let log = TempLog::new("bom", &contents);
+        let mut follower = Follower::new(log.0.clone());
+        follower.open(true).unwrap();
+
+        assert_eq!(follower.read_lines(10).unwrap(), ["first", "second"]);
+    }
+
+    #[test]
+    fn truncation_rewinds_to_the_start() {
+        let log = TempLog::new("truncate", b"\xEF\xBB\xBFone\ntwo\nthree\n");
+        let mut follower = Follower::new(log.0.clone());
+        follower.open(true).unwrap();
+        assert_eq!(follower.read_lines(10).unwrap(), ["one", "two", "three"]);
+        assert_eq!(follower.detect_change(), None);
+
+        fs::write(&log.0, b"\xEF\xBB\xBFnew\n").unwrap();
+        assert_eq!(follower.detect_change(), Some(FileChange::Truncated));
+
+        follower.rewind();
+        assert_eq!(follower.position, 3);
+        assert_eq!(follower.read_lines(10).unwrap(), ["new"]);
+    }
+
+    #[test]
+    fn a_new_file_under_the_same_name_is_a_rotation() {
+        let log = TempLog::new("rotate", b"old\n");
+        let mut follower = Follower::new(log.0.clone());
+        follower.open(true).unwrap();
+        assert_eq!(follower.read_lines(10).unwrap(), ["old"]);
+
+        // Renamed instead of deleted so the new file cannot reuse the old one's identity
+        fs::rename(&log.0, log.0.with_extension("log.1")).unwrap();
+        fs::write(&log.0, b"a longer first line\n").unwrap();
+        assert_eq!(follower.detect_change(), Some(FileChange::Rotated));
+
+        fs::remove_file(&log.0).unwrap();
+        assert_eq!(follower.detect_change(), Some(FileChange::Missing));
+    }
+}
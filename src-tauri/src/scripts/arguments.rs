@@ -0,0 +1,344 @@
+use chrono::Local;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use super::structs::ArgumentType;
+use crate::utils::secrets::is_secret_reference;
+
+lazy_static! {
+    static ref PLACEHOLDER_REGEX: Regex =
+        Regex::new(r"\{(date|time|datetime|timestamp|clipboard|prompt)(?::([^}]+))?\}").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgumentKind {
+    Text,
+    Number,
+    Flag,
+    Choice,
+    File,
+    Directory,
+    Secret,
+}
+
+#[derive(Debug)]
+pub enum ArgumentError {
+    Invalid(String),
+    // Labels of the arguments that need a value typed in before the run
+    PromptRequired(Vec<String>),
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgumentError::Invalid(message) => write!(f, "{}", message),
+            ArgumentError::PromptRequired(labels) => {
+                write!(f, "Values required for: {}", labels.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArgumentError {}
+
+impl ArgumentType {
+    // `value` holds the type the window picked, "label" is the original name of a flag
+    pub fn kind(&self) -> ArgumentKind {
+        match self.value.as_str() {
+            "number" => ArgumentKind::Number,
+            "label" | "flag" => ArgumentKind::Flag,
+            "choice" => ArgumentKind::Choice,
+            "file" => ArgumentKind::File,
+            "folder" | "directory" => ArgumentKind::Directory,
+            "secret" => ArgumentKind::Secret,
+            _ => ArgumentKind::Text,
+        }
+    }
+}
+
+fn read_clipboard() -> Result<String, ArgumentError> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|e| ArgumentError::Invalid(format!("Failed to read clipboard: {}", e)))
+}
+
+// Replaces {date}, {time}, {datetime}, {timestamp}, {clipboard} and {prompt} / {prompt:Label}
+fn expand_placeholders(
+    value: &str,
+    label: &str,
+    prompt_values: &HashMap<String, String>,
+    missing_prompts: &mut Vec<String>,
+) -> Result<String, ArgumentError> {
+    if !PLACEHOLDER_REGEX.is_match(value) {
+        return Ok(value.to_string());
+    }
+
+    let now = Local::now();
+    let mut error = None;
+    let expanded =
+        PLACEHOLDER_REGEX.replace_all(value, |captures: &regex::Captures| match &captures[1] {
+            "date" => now.format("%Y-%m-%d").to_string(),
+            "time" => now.format("%H-%M-%S").to_string(),
+            "datetime" => now.format("%Y-%m-%d_%H-%M-%S").to_string(),
+            "timestamp" => now.timestamp().to_string(),
+            "clipboard" => read_clipboard().unwrap_or_else(|e| {
+                error = Some(e);
+                String::new()
+            }),
+            _ => {
+                let prompt_label = captures
+                    .get(2)
+                    .map(|name| name.as_str())
+                    .unwrap_or(label)
+                    .to_string();
+                match prompt_values.get(&prompt_label) {
+                    Some(value) => value.clone(),
+                    None => {
+                        if !missing_prompts.contains(&prompt_label) {
+                            missing_prompts.push(prompt_label);
+                        }
+                        String::new()
+                    }
+                }
+            }
+        });
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+fn validate(definition: &ArgumentType, value: &str) -> Result<(), ArgumentError> {
+    let invalid = |message: &str| {
+        Err(ArgumentError::Invalid(format!(
+            "Argument '{}' {}",
+            definition.label, message
+        )))
+    };
+
+    match definition.kind() {
+        ArgumentKind::Number if value.parse::<f64>().is_err() => invalid("must be a number"),
+        ArgumentKind::Choice if !definition.options.iter().any(|option| option == value) => {
+            invalid(&format!(
+                "must be one of: {}",
+                definition.options.join(", ")
+            ))
+        }
+        ArgumentKind::File if !Path::new(value).is_file() => {
+            invalid(&format!("points to a missing file: {}", value))
+        }
+        ArgumentKind::Directory if !Path::new(value).is_dir() => {
+            invalid(&format!("points to a missing folder: {}", value))
+        }
+        ArgumentKind::Secret if !is_secret_reference(value) => {
+            invalid("must reference a stored secret")
+        }
+        _ => Ok(()),
+    }
+}
+
+// Turns the saved definitions and the values of this run into an argv vector,
+// each value stays a single argument so quotes and spaces need no escaping
+pub fn build_argv(
+    definitions: &[ArgumentType],
+    values: &[Option<String>],
+    prompt_values: &HashMap<String, String>,
+) -> Result<Vec<String>, ArgumentError> {
+    let mut argv = vec![];
+    let mut missing_prompts = vec![];
+
+    for (index, definition) in definitions.iter().enumerate() {
+        let value = values
+            .get(index)
+            .cloned()
+            .flatten()
+            .filter(|value| !value.is_empty())
+            .or_else(|| definition.default.clone())
+            .unwrap_or_default();
+
+        if definition.kind() == ArgumentKind::Flag {
+            // The window sends the label back for a checked flag
+            let enabled = value == definition.label || value == "true" || value == "1";
+            if enabled {
+                argv.push(
+                    definition
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| definition.label.clone()),
+                );
+            }
+            continue;
+        }
+
+        let prompts_before = missing_prompts.len();
+        let value = expand_placeholders(
+            &value,
+            &definition.label,
+            prompt_values,
+            &mut missing_prompts,
+        )?;
+
+        // The value is incomplete until the prompt is answered, the run asks for it first
+        if missing_prompts.len() > prompts_before {
+            continue;
+        }
+
+        if value.is_empty() {
+            if definition.required {
+                return Err(ArgumentError::Invalid(format!(
+                    "Argument '{}' is required",
+                    definition.label
+                )));
+            }
+            continue;
+        }
+
+        validate(definition, &value)?;
+
+        if let Some(name) = definition.name.as_ref().filter(|name| !name.is_empty()) {
+            argv.push(name.clone());
+        }
+        argv.push(value);
+    }
+
+    if !missing_prompts.is_empty() {
+        return Err(ArgumentError::PromptRequired(missing_prompts));
+    }
+
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argument(label: &str, kind: &str) -> ArgumentType {
+        ArgumentType {
+            label: label.to_string(),
+            value: kind.to_string(),
+            name: None,
+            default: None,
+            required: false,
+            options: vec![],
+        }
+    }
+
+    fn values(values: &[&str]) -> Vec<Option<String>> {
+        values.iter().map(|value| Some(value.to_string())).collect()
+    }
+
+    #[test]
+    fn values_with_spaces_stay_single_arguments() {
+        let mut output = argument("Output", "text");
+        output.name = Some("--output".to_string());
+        let definitions = vec![output, argument("Message", "text")];
+
+        let argv = build_argv(
+            &definitions,
+            &values(&["my file.txt", "say \"hi\""]),
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(argv, vec!["--output", "my file.txt", "say \"hi\""]);
+    }
+
+    #[test]
+    fn flags_pass_their_name_only_when_checked() {
+        let mut verbose = argument("Verbose", "label");
+        verbose.name = Some("-v".to_string());
+        let definitions = vec![verbose, argument("Dry run", "label")];
+
+        let argv = build_argv(&definitions, &values(&["Verbose", ""]), &HashMap::new()).unwrap();
+
+        assert_eq!(argv, vec!["-v"]);
+    }
+
+    #[test]
+    fn defaults_fill_empty_values_and_numbers_are_checked() {
+        let mut count = argument("Count", "number");
+        count.default = Some("3".to_string());
+        let definitions = vec![count];
+
+        assert_eq!(
+            build_argv(&definitions, &values(&[""]), &HashMap::new()).unwrap(),
+            vec!["3"]
+        );
+        assert!(matches!(
+            build_argv(&definitions, &values(&["three"]), &HashMap::new()),
+            Err(ArgumentError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn choices_must_be_one_of_the_options() {
+        let mut mode = argument("Mode", "choice");
+        mode.options = vec!["fast".to_string(), "slow".to_string()];
+        let definitions = vec![mode];
+
+        assert!(build_argv(&definitions, &values(&["fast"]), &HashMap::new()).is_ok());
+        assert!(matches!(
+            build_argv(&definitions, &values(&["medium"]), &HashMap::new()),
+            Err(ArgumentError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn required_arguments_without_a_value_fail() {
+        let mut name = argument("Name", "text");
+        name.required = true;
+
+        assert!(matches!(
+            build_argv(&[name], &[None], &HashMap::new()),
+            Err(ArgumentError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn required_prompt_arguments_ask_for_their_value() {
+        let mut name = argument("Name", "text");
+        name.required = true;
+        name.default = Some("{prompt}".to_string());
+        let mut count = argument("Count", "number");
+        count.default = Some("{prompt:How many}".to_string());
+        let definitions = vec![name, count];
+
+        match build_argv(&definitions, &[None, None], &HashMap::new()) {
+            Err(ArgumentError::PromptRequired(labels)) => {
+                assert_eq!(labels, vec!["Name", "How many"])
+            }
+            other => panic!("expected a prompt, got {:?}", other),
+        }
+
+        let answers = HashMap::from([
+            ("Name".to_string(), "Ada".to_string()),
+            ("How many".to_string(), "2".to_string()),
+        ]);
+        assert_eq!(
+            build_argv(&definitions, &[None, None], &answers).unwrap(),
+            vec!["Ada", "2"]
+        );
+    }
+
+    #[test]
+    fn placeholders_expand_inside_values() {
+        let mut missing = vec![];
+
+        let expanded =
+            expand_placeholders("log-{date}.txt", "File", &HashMap::new(), &mut missing).unwrap();
+
+        assert_eq!(
+            expanded,
+            format!("log-{}.txt", Local::now().format("%Y-%m-%d"))
+        );
+        assert!(missing.is_empty());
+        assert_eq!(
+            expand_placeholders("plain {unknown}", "File", &HashMap::new(), &mut missing).unwrap(),
+            "plain {unknown}"
+        );
+    }
+}
@@ -1,3 +1,32 @@
 pub const SCRIPTS_FOLDER: &str = "scripts";
 pub const SCRIPT_FILES_FOLDER: &str = "files";
+pub const SCRIPT_LOGS_FOLDER: &str = "logs";
 pub const SCRIPTS_DB: &str = "scripts-info";
+pub const SCRIPTS_HISTORY_DB: &str = "scripts-history";
+// Runs kept per script, older ones are removed together with their logs
+pub const MAX_HISTORY_PER_SCRIPT: usize = 50;
+pub const MAX_HISTORY_AGE_DAYS: u64 = 30;
+// Seconds a script gets to exit after a graceful stop before it is killed
+pub const STOP_TIMEOUT_SECS: u64 = 5;
+// Milliseconds between checks of a run's time and memory limits
+pub const LIMITS_TICK_MILLIS: u64 = 500;
+// Largest memory limit a script can be saved with, 1 TB
+pub const MAX_MEMORY_LIMIT_MB: u64 = 1024 * 1024;
+// Seconds between running status events for live scripts
+pub const STATUS_INTERVAL_SECS: u64 = 5;
+pub const SCRIPTS_SCHEDULES_DB: &str = "scripts-schedules";
+// Seconds between checks for due schedules
+pub const SCHEDULER_TICK_SECS: u64 = 5;
+// Longest interval a schedule can be saved with, a year
+pub const MAX_INTERVAL_SECS: u64 = 366 * 24 * 60 * 60;
+// Upper bound of missed runs replayed by the run_all policy
+pub const MAX_MISSED_RUNS: usize = 10;
+pub const SCRIPTS_PIPELINES_DB: &str = "scripts-pipelines";
+// Finished pipeline runs kept in memory for get_pipeline_run, older ones are dropped
+pub const MAX_FINISHED_PIPELINE_RUNS: usize = 100;
+// Milliseconds between checks for debounced watch triggers
+pub const WATCH_TICK_MILLIS: u64 = 100;
+// Milliseconds a watch rule keeps ignoring changes after the run it started ended
+pub const WATCH_COOLDOWN_MILLIS: u64 = 1000;
+// Layout version of scripts-info.json
+pub const SCRIPTS_DB_VERSION: u32 = 1;
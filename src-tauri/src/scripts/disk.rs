@@ -1,126 +1,332 @@
+use lazy_static::lazy_static;
 use std::env;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 use crate::scripts::structs::ScriptSave;
 use crate::scripts::structs::ScriptSaveLocal;
 
-use super::constants::{SCRIPTS_DB, SCRIPTS_FOLDER, SCRIPT_FILES_FOLDER};
-use super::structs::ArgumentType;
+use super::arguments::ArgumentKind;
+use super::constants::{
+    MAX_MEMORY_LIMIT_MB, SCRIPTS_DB, SCRIPTS_DB_VERSION, SCRIPTS_FOLDER, SCRIPT_FILES_FOLDER,
+    SCRIPT_LOGS_FOLDER,
+};
+use super::error::{path_context, report_error, ScriptError, ScriptResult};
+use super::integrity::hash_file;
+use super::process::now_millis;
+use super::structs::{
+    ArgumentType, ResourceLimits, ScriptIntegrity, ScriptMeta, ScriptOptions, ScriptQuery,
+    ScriptSort, ScriptsDb, WatchRule,
+};
+use crate::utils::secrets::{is_secret_reference, save_secret};
+
+lazy_static! {
+    // Held from reading the database until the changed version is written back,
+    // runs, watches, schedules and the API all update it
+    static ref SCRIPTS_DB_LOCK: Mutex<()> = Mutex::new(());
+}
 
-pub fn get_scripts_folder_path() -> PathBuf {
-    let current_dir = env::current_dir().expect("Failed to get current directory");
-    let folder_path = current_dir.join(SCRIPTS_FOLDER);
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
 
-    if !folder_path.exists() {
-        fs::create_dir_all(&folder_path).unwrap();
-        println!("Folder scripts created successfully.");
+// Creates the folder when it is missing, a failure shows up on the first file operation inside it
+fn ensure_folder(folder_path: &Path, name: &str) {
+    if folder_path.exists() {
+        return;
     }
 
-    return folder_path;
+    match fs::create_dir_all(folder_path) {
+        Ok(_) => println!("Folder {} created successfully.", name),
+        Err(e) => report_error(&ScriptError::io(path_context("create", folder_path), e)),
+    }
+}
+
+pub fn get_scripts_folder_path() -> PathBuf {
+    let current_dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let folder_path = current_dir.join(SCRIPTS_FOLDER);
+    ensure_folder(&folder_path, "scripts");
+
+    folder_path
 }
 
 pub fn get_script_files_folder_path() -> PathBuf {
-    let scripts_folder = get_scripts_folder_path();
-    let file_folder_path = scripts_folder.join(SCRIPT_FILES_FOLDER);
+    let file_folder_path = get_scripts_folder_path().join(SCRIPT_FILES_FOLDER);
+    ensure_folder(&file_folder_path, "files");
 
-    if !file_folder_path.exists() {
-        fs::create_dir_all(&file_folder_path).unwrap();
-        println!("Folder files created successfully.");
-    }
+    file_folder_path
+}
 
-    return file_folder_path;
+pub fn get_script_logs_folder_path() -> PathBuf {
+    let logs_folder_path = get_scripts_folder_path().join(SCRIPT_LOGS_FOLDER);
+    ensure_folder(&logs_folder_path, "logs");
+
+    logs_folder_path
 }
 
 pub fn get_scripts_db_path() -> PathBuf {
-    let scripts_folder = get_scripts_folder_path();
-    let db_file_path = scripts_folder.join(format!("{0}.json", &SCRIPTS_DB));
+    get_scripts_folder_path().join(format!("{0}.json", &SCRIPTS_DB))
+}
 
-    if !db_file_path.exists() {
-        // Serialize an empty array instead of an empty object
-        let data = serde_json::to_string_pretty(&Vec::<ScriptSave>::new()).unwrap();
-        fs::write(db_file_path.clone(), data).unwrap();
-        println!("Script database created successfully.");
+// Copies carry part of the content hash in the name so two different files called the same never
+// overwrite each other
+pub fn get_new_script_path(script_path: &str, content_hash: &str) -> ScriptResult<PathBuf> {
+    let stem = file_stem(script_path)?;
+    let short_hash = &content_hash[..content_hash.len().min(12)];
+    let file_name = match Path::new(script_path).extension() {
+        Some(extension) => format!("{}-{}.{}", stem, short_hash, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, short_hash),
+    };
+
+    Ok(get_script_files_folder_path().join(file_name))
+}
+
+// Returns the path of the copy and the hash of its content, an identical copy is reused
+fn copy_to_scripts_folder(script_path: &str) -> ScriptResult<(PathBuf, String)> {
+    let content_hash = hash_file(Path::new(script_path))?;
+    let new_path = get_new_script_path(script_path, &content_hash)?;
+
+    if new_path.exists() && hash_file(&new_path).ok().as_ref() == Some(&content_hash) {
+        println!("Script already exists.");
+        return Ok((new_path, content_hash));
     }
-    return db_file_path;
+
+    fs::copy(script_path, &new_path)
+        .map_err(|e| ScriptError::io(path_context("copy", Path::new(script_path)), e))?;
+
+    Ok((new_path, content_hash))
 }
 
-pub fn get_new_script_path(script_path: String) -> PathBuf {
-    let scripts_files_folder = get_script_files_folder_path();
-    scripts_files_folder.join(Path::new(&script_path).file_name().unwrap())
+// Written to a temporary file first so a crash never leaves a half written file, the
+// name is unique so concurrent writers never share a temporary file
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(
+        "{}.{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+
+    fs::write(&temp_path, data)?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        e
+    })
 }
 
-fn save_scripts_to_db(scripts: Vec<ScriptSave>) {
+fn save_scripts_to_db(scripts: Vec<ScriptSave>) -> ScriptResult<()> {
     let db_file_path = get_scripts_db_path();
-    let data = serde_json::to_string_pretty(&scripts).unwrap();
-    fs::write(&db_file_path, data.as_bytes()).unwrap();
+    let data = serde_json::to_string_pretty(&ScriptsDb {
+        version: SCRIPTS_DB_VERSION,
+        scripts,
+    })?;
+
+    write_atomically(&db_file_path, data.as_bytes())
+        .map_err(|e| ScriptError::io(path_context("write", &db_file_path), e))
 }
 
-fn get_scripts_db() -> Vec<ScriptSave> {
-    let db_file_path = get_scripts_db_path();
-    let db_file = fs::File::open(db_file_path.clone()).unwrap();
-    let scripts: Vec<ScriptSave> = serde_json::from_reader(db_file).unwrap();
+// Brings an older database layout up to SCRIPTS_DB_VERSION, one version at a time. A newer
+// layout is refused, saving it back would silently drop the fields this build does not know.
+fn migrate_scripts_db(mut data: serde_json::Value) -> ScriptResult<(serde_json::Value, bool)> {
+    let found = data
+        .get("version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0);
+    if found > SCRIPTS_DB_VERSION as u64 {
+        return Err(ScriptError::NewerDatabase(found));
+    }
 
-    scripts
+    let mut version = found as u32;
+    let migrated = version < SCRIPTS_DB_VERSION;
+
+    while version < SCRIPTS_DB_VERSION {
+        data = match version {
+            // Version 0 was a plain list, the new script fields all have defaults
+            0 => serde_json::json!({ "version": 1, "scripts": data }),
+            _ => data,
+        };
+        version += 1;
+    }
+
+    Ok((data, migrated))
 }
 
-pub fn add_script_to_disk(script_path: String) {
-    let new_path = get_new_script_path(script_path.clone());
+// Keeps every script entry that still parses, returns None when nothing is usable
+fn recover_scripts(data: &serde_json::Value) -> Option<Vec<ScriptSave>> {
+    let entries = data.get("scripts")?.as_array()?;
 
-    if new_path.exists() {
-        println!("Script already exists.");
-        return;
+    Some(
+        entries
+            .iter()
+            .filter_map(|entry| serde_json::from_value::<ScriptSave>(entry.clone()).ok())
+            .collect(),
+    )
+}
+
+// Moves the unreadable database aside, saves what could be recovered and tells the window
+fn recover_corrupt_db(
+    db_file_path: &Path,
+    scripts: Vec<ScriptSave>,
+    message: String,
+) -> ScriptResult<Vec<ScriptSave>> {
+    let backup = db_file_path.with_extension(format!("corrupt-{}.json", now_millis()));
+    fs::copy(db_file_path, &backup)
+        .map_err(|e| ScriptError::io(path_context("back up", db_file_path), e))?;
+
+    save_scripts_to_db(scripts.clone())?;
+    report_error(&ScriptError::CorruptDatabase { backup, message });
+
+    Ok(scripts)
+}
+
+// Callers hold SCRIPTS_DB_LOCK, this may write the database while migrating or recovering it
+fn get_scripts_db() -> ScriptResult<Vec<ScriptSave>> {
+    let db_file_path = get_scripts_db_path();
+    if !db_file_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = fs::read_to_string(&db_file_path)
+        .map_err(|e| ScriptError::io(path_context("read", &db_file_path), e))?;
+
+    let data = match serde_json::from_str::<serde_json::Value>(&content) {
+        Ok(data) => data,
+        Err(e) => return recover_corrupt_db(&db_file_path, vec![], e.to_string()),
+    };
+
+    let (data, migrated) = migrate_scripts_db(data)?;
+    let db = match serde_json::from_value::<ScriptsDb>(data.clone()) {
+        Ok(db) => db,
+        Err(e) => {
+            let scripts = recover_scripts(&data).unwrap_or_default();
+            return recover_corrupt_db(&db_file_path, scripts, e.to_string());
+        }
+    };
+
+    if migrated {
+        // Keep the old file around in case the migration needs to be undone
+        let backup_path = db_file_path.with_extension("pre-migration.json");
+        fs::copy(&db_file_path, &backup_path)
+            .map_err(|e| ScriptError::io(path_context("back up", &db_file_path), e))?;
+        save_scripts_to_db(db.scripts.clone())?;
+        println!("Script database migrated to version {}.", db.version);
+    }
+
+    Ok(db.scripts)
+}
+
+// Creates an empty database on first start and recovers a corrupt one early
+pub fn ensure_scripts_db() -> ScriptResult<()> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    if !get_scripts_db_path().exists() {
+        save_scripts_to_db(vec![])?;
+        println!("Script database created successfully.");
     }
 
-    fs::copy(&script_path, &new_path).unwrap();
+    get_scripts_db().map(|_| ())
+}
 
-    let scrip_name = file_stem(script_path.to_string()).unwrap();
-    let mut scripts = get_scripts_db();
+pub fn add_script_to_disk(script_path: &str) -> ScriptResult<()> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let (new_path, content_hash) = copy_to_scripts_folder(script_path)?;
+    let mut scripts = get_scripts_db()?;
 
+    // Every entry that pointed at the original now runs the copy
     for script in scripts.iter_mut() {
-        if file_stem(script.path.to_string()).unwrap() == scrip_name {
-            script.path = new_path.to_str().unwrap().to_string();
+        if script.path == script_path {
+            script.path = new_path.to_string_lossy().to_string();
+            script.integrity = ScriptIntegrity {
+                content_hash: Some(content_hash.clone()),
+                source_path: Some(script_path.to_string()),
+            };
         }
     }
 
-    save_scripts_to_db(scripts);
+    save_scripts_to_db(scripts)?;
 
     println!("Script saved successfully.");
+
+    Ok(())
 }
 
-pub fn remove_script(
-    name: String,
-    script_path: String,
-    remove_from_disk: bool,
-) -> Result<(), std::io::Error> {
+pub fn remove_script(name: &str, script_path: &str, remove_from_disk: bool) -> ScriptResult<()> {
     let current_folder = get_scripts_folder_path();
-    let is_local = is_path_local(&script_path, &current_folder);
+    let is_local = is_path_local(script_path, &current_folder);
 
     if is_local && remove_from_disk {
-        let file_path = Path::new(&script_path);
+        let file_path = Path::new(script_path);
         // Check if the script file exists and remove it
         if file_path.exists() {
-            fs::remove_file(&file_path).unwrap();
+            fs::remove_file(file_path)
+                .map_err(|e| ScriptError::io(path_context("remove", file_path), e))?;
             println!("Script {} removed successfully.", script_path);
         } else {
             println!("Script {} does not exist.", script_path);
         }
     }
 
-    let mut scripts = get_scripts_db();
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?;
 
     if remove_from_disk {
         // Remove the script with the same path
-        scripts.retain(|script| script.path.to_string() != script_path.to_string());
+        scripts.retain(|script| script.path != script_path);
     } else {
         // Remove the script with the matching name
         scripts.retain(|script| script.name != name);
     }
 
-    save_scripts_to_db(scripts);
+    save_scripts_to_db(scripts)
+}
 
-    Ok(())
+// Secret defaults are moved to the secret store so scripts-info.json only keeps a reference
+fn protect_secret_defaults(
+    name: &str,
+    script_args: Vec<ArgumentType>,
+) -> ScriptResult<Vec<ArgumentType>> {
+    script_args
+        .into_iter()
+        .map(|mut argument| {
+            if argument.kind() == ArgumentKind::Secret {
+                if let Some(default) = argument
+                    .default
+                    .as_ref()
+                    .filter(|default| !default.is_empty() && !is_secret_reference(default))
+                {
+                    let label = format!("{} {}", name, argument.label);
+                    let reference = save_secret(&label, default, None)
+                        .map_err(|e| ScriptError::Secret(e.to_string()))?;
+                    argument.default = Some(reference);
+                }
+            }
+            Ok(argument)
+        })
+        .collect()
+}
+
+// A zero limit would end every run at once, a huge one overflows once it is turned into bytes
+fn validate_limits(limits: &ResourceLimits) -> ScriptResult<()> {
+    if limits.max_runtime_secs == Some(0) {
+        return Err(ScriptError::InvalidLimits(
+            "the time limit has to be at least one second".to_string(),
+        ));
+    }
+
+    match limits.max_memory_mb {
+        Some(0) => Err(ScriptError::InvalidLimits(
+            "the memory limit has to be at least 1 MB".to_string(),
+        )),
+        Some(mb) if mb > MAX_MEMORY_LIMIT_MB => Err(ScriptError::InvalidLimits(format!(
+            "the memory limit can be at most {} MB",
+            MAX_MEMORY_LIMIT_MB
+        ))),
+        _ => Ok(()),
+    }
 }
 
 pub fn save_script(
@@ -128,44 +334,57 @@ pub fn save_script(
     name: String,
     script_args: Vec<ArgumentType>,
     save_to_disk: bool,
-) -> io::Result<()> {
-    let file_path = if save_to_disk {
-        add_script_to_disk(script_path.clone());
-        get_new_script_path(script_path.clone())
-    } else {
-        Path::new(&script_path).to_path_buf()
-    };
+    options: ScriptOptions,
+    meta: ScriptMeta,
+) -> ScriptResult<()> {
+    validate_limits(&options.limits)?;
 
-    let mut scripts = get_scripts_db();
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?;
 
     // Check if the script already exists
     if scripts.iter().any(|script| script.name == name) {
-        return Err(io::Error::new(
-            io::ErrorKind::AlreadyExists,
-            "Script already exists.",
-        ));
+        return Err(ScriptError::AlreadyExists(name));
     }
 
+    let (file_path, integrity) = if save_to_disk {
+        let (new_path, content_hash) = copy_to_scripts_folder(&script_path)?;
+        let integrity = ScriptIntegrity {
+            content_hash: Some(content_hash),
+            source_path: Some(script_path),
+        };
+        (new_path, integrity)
+    } else {
+        // A file that cannot be read yet is hashed on its first run
+        let integrity = ScriptIntegrity {
+            content_hash: hash_file(Path::new(&script_path)).ok(),
+            source_path: None,
+        };
+        (PathBuf::from(&script_path), integrity)
+    };
+
+    let script_args = protect_secret_defaults(&name, script_args)?;
+
     // Add the new script to the list
     scripts.push(ScriptSave {
         name,
         script_args,
-        path: file_path.to_str().unwrap().to_string(),
+        path: file_path.to_string_lossy().to_string(),
+        options,
+        meta: ScriptMeta {
+            last_used_at: None,
+            ..meta
+        },
+        integrity,
     });
 
-    save_scripts_to_db(scripts);
-
-    Ok(())
+    save_scripts_to_db(scripts)
 }
 
-pub fn get_scripts_string() -> Result<String, io::Error> {
-    let scripts = get_scripts_db();
-
+fn to_local_scripts(scripts: Vec<ScriptSave>) -> Vec<ScriptSaveLocal> {
     let current_folder = get_scripts_folder_path();
 
-    // map over scripts and add local value to each script
-
-    let scripts_local = scripts
+    scripts
         .into_iter()
         .map(|script| {
             let is_local = is_path_local(&script.path, &current_folder);
@@ -174,13 +393,206 @@ pub fn get_scripts_string() -> Result<String, io::Error> {
                 script_args: script.script_args,
                 path: script.path,
                 local: is_local,
+                options: script.options,
+                meta: script.meta,
+                integrity: script.integrity,
             }
         })
-        .collect::<Vec<ScriptSaveLocal>>();
+        .collect::<Vec<ScriptSaveLocal>>()
+}
+
+pub fn get_scripts_string() -> ScriptResult<String> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let scripts_local = to_local_scripts(get_scripts_db()?);
+
+    Ok(serde_json::to_string(&scripts_local)?)
+}
+
+fn matches_query(script: &ScriptSave, query: &ScriptQuery, text: &str) -> bool {
+    let text_matches = text.is_empty()
+        || [&script.name, &script.meta.description, &script.path]
+            .iter()
+            .any(|field| field.to_lowercase().contains(text));
+
+    let tags_matches = query.tags.iter().all(|tag| {
+        script
+            .meta
+            .tags
+            .iter()
+            .any(|script_tag| script_tag.eq_ignore_ascii_case(tag))
+    });
+
+    let group_matches = match query.group.as_ref().filter(|group| !group.is_empty()) {
+        Some(group) => script.meta.group.as_ref().map_or(false, |script_group| {
+            script_group == group || script_group.starts_with(&format!("{}/", group))
+        }),
+        None => true,
+    };
+
+    let favorite_matches = query
+        .favorite
+        .map_or(true, |favorite| script.meta.favorite == favorite);
+
+    text_matches && tags_matches && group_matches && favorite_matches
+}
+
+pub fn query_scripts(query: &ScriptQuery) -> ScriptResult<Vec<ScriptSaveLocal>> {
+    let text = query
+        .text
+        .as_ref()
+        .map(|text| text.trim().to_lowercase())
+        .unwrap_or_default();
+
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?
+        .into_iter()
+        .filter(|script| matches_query(script, query, &text))
+        .collect::<Vec<ScriptSave>>();
+
+    match query.sort {
+        ScriptSort::SortOrder => scripts.sort_by(|a, b| {
+            b.meta
+                .favorite
+                .cmp(&a.meta.favorite)
+                .then(a.meta.sort_order.cmp(&b.meta.sort_order))
+                .then(a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        }),
+        ScriptSort::Name => {
+            scripts.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        }
+        ScriptSort::LastUsed => {
+            scripts.sort_by(|a, b| b.meta.last_used_at.cmp(&a.meta.last_used_at))
+        }
+    }
 
-    let output = serde_json::to_string(&scripts_local).expect("Failed to serialize shortcuts");
+    Ok(to_local_scripts(scripts))
+}
 
-    Ok(output)
+// Replaces the organisation fields of a script, the last used time stays as it is
+pub fn update_script_meta(name: &str, meta: ScriptMeta) -> ScriptResult<()> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?;
+    let script = scripts
+        .iter_mut()
+        .find(|script| script.name == name)
+        .ok_or_else(|| ScriptError::NotFound(name.to_string()))?;
+    script.meta = ScriptMeta {
+        last_used_at: script.meta.last_used_at,
+        ..meta
+    };
+
+    save_scripts_to_db(scripts)
+}
+
+pub fn mark_script_used(name: Option<&str>, path: &str, used_at: u64) {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let result = get_scripts_db().and_then(|mut scripts| {
+        let by_name = name.and_then(|name| scripts.iter().position(|script| script.name == name));
+        let Some(index) = by_name.or_else(|| scripts.iter().position(|script| script.path == path))
+        else {
+            return Ok(());
+        };
+        scripts[index].meta.last_used_at = Some(used_at);
+
+        save_scripts_to_db(scripts)
+    });
+
+    if let Err(e) = result {
+        report_error(&e);
+    }
+}
+
+pub fn set_watch_rules(name: &str, rules: Vec<WatchRule>) -> ScriptResult<()> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?;
+    let script = scripts
+        .iter_mut()
+        .find(|script| script.name == name)
+        .ok_or_else(|| ScriptError::NotFound(name.to_string()))?;
+    script.options.watch = rules;
+
+    save_scripts_to_db(scripts)
+}
+
+pub fn set_content_hash(name: &str, content_hash: String) -> ScriptResult<()> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?;
+    let script = scripts
+        .iter_mut()
+        .find(|script| script.name == name)
+        .ok_or_else(|| ScriptError::NotFound(name.to_string()))?;
+    script.integrity.content_hash = Some(content_hash);
+
+    save_scripts_to_db(scripts)
+}
+
+// Accepts the file as it is now, later runs compare against this content
+pub fn accept_script_changes(name: &str) -> ScriptResult<()> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?;
+    let script = scripts
+        .iter_mut()
+        .find(|script| script.name == name)
+        .ok_or_else(|| ScriptError::NotFound(name.to_string()))?;
+    script.integrity.content_hash = Some(hash_file(Path::new(&script.path))?);
+
+    save_scripts_to_db(scripts)
+}
+
+// Copies the original file into the scripts folder again, the previous copy is removed once no
+// other script uses it
+pub fn update_script_copy(name: &str) -> ScriptResult<()> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    let mut scripts = get_scripts_db()?;
+    let index = scripts
+        .iter()
+        .position(|script| script.name == name)
+        .ok_or_else(|| ScriptError::NotFound(name.to_string()))?;
+    let source_path = scripts[index]
+        .integrity
+        .source_path
+        .clone()
+        .ok_or_else(|| ScriptError::InvalidPath(scripts[index].path.clone()))?;
+
+    let (new_path, content_hash) = copy_to_scripts_folder(&source_path)?;
+    let new_path = new_path.to_string_lossy().to_string();
+    let old_path = std::mem::replace(&mut scripts[index].path, new_path.clone());
+    scripts[index].integrity.content_hash = Some(content_hash);
+
+    let still_used = scripts.iter().any(|script| script.path == old_path);
+    save_scripts_to_db(scripts)?;
+
+    if old_path != new_path
+        && !still_used
+        && is_path_local(&old_path, &get_script_files_folder_path())
+    {
+        let old_file = Path::new(&old_path);
+        if old_file.exists() {
+            fs::remove_file(old_file)
+                .map_err(|e| ScriptError::io(path_context("remove", old_file), e))?;
+        }
+    }
+
+    println!("Script {} copy updated from {}.", name, source_path);
+
+    Ok(())
+}
+
+// Read failures are reported to the window and treated as an empty database
+pub fn list_scripts() -> Vec<ScriptSave> {
+    let _lock = SCRIPTS_DB_LOCK.lock().unwrap();
+    get_scripts_db().unwrap_or_else(|e| {
+        report_error(&e);
+        vec![]
+    })
+}
+
+pub fn find_script(name: Option<&str>, path: &str) -> Option<ScriptSave> {
+    let scripts = list_scripts();
+    let by_name = name.and_then(|name| scripts.iter().position(|script| script.name == name));
+    let index = by_name.or_else(|| scripts.iter().position(|script| script.path == path))?;
+
+    scripts.into_iter().nth(index)
 }
 
 // Helper function to determine if the script path is local to the current folder
@@ -190,13 +602,86 @@ fn is_path_local(script_path: &str, current_folder: &PathBuf) -> bool {
     script_path.starts_with(current_folder)
 }
 
-pub fn file_stem(file_path: String) -> Result<String, io::Error> {
-    let path = Path::new(&file_path);
-    let name = path
+pub fn file_stem(file_path: &str) -> ScriptResult<String> {
+    let name = Path::new(file_path)
         .file_stem()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File name not found"))?
+        .ok_or_else(|| ScriptError::InvalidPath(file_path.to_string()))?
         .to_string_lossy()
         .into_owned();
 
     Ok(name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_lists_are_migrated_to_the_current_version() {
+        let legacy = serde_json::json!([
+            { "name": "backup", "script_args": [], "path": "backup.ps1" }
+        ]);
+
+        let (data, migrated) = migrate_scripts_db(legacy).unwrap();
+        let db = serde_json::from_value::<ScriptsDb>(data).unwrap();
+
+        assert!(migrated);
+        assert_eq!(db.version, SCRIPTS_DB_VERSION);
+        assert_eq!(db.scripts.len(), 1);
+        assert_eq!(db.scripts[0].name, "backup");
+        assert!(db.scripts[0].meta.tags.is_empty());
+        assert_eq!(db.scripts[0].meta.last_used_at, None);
+    }
+
+    #[test]
+    fn current_databases_are_left_alone() {
+        let current = serde_json::json!({ "version": SCRIPTS_DB_VERSION, "scripts": [] });
+
+        let (data, migrated) = migrate_scripts_db(current.clone()).unwrap();
+
+        assert!(!migrated);
+        assert_eq!(data, current);
+    }
+
+    #[test]
+    fn databases_from_a_newer_build_are_refused() {
+        let newer = serde_json::json!({ "version": SCRIPTS_DB_VERSION + 1, "scripts": [] });
+
+        assert!(matches!(
+            migrate_scripts_db(newer),
+            Err(ScriptError::NewerDatabase(version)) if version == SCRIPTS_DB_VERSION as u64 + 1
+        ));
+    }
+
+    #[test]
+    fn recovery_keeps_the_entries_that_still_parse() {
+        let data = serde_json::json!({
+            "version": SCRIPTS_DB_VERSION,
+            "scripts": [
+                { "name": "good", "script_args": [], "path": "good.sh" },
+                { "name": 42 },
+            ]
+        });
+
+        let scripts = recover_scripts(&data).unwrap();
+
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].name, "good");
+        assert!(recover_scripts(&serde_json::json!({ "other": [] })).is_none());
+    }
+
+    #[test]
+    fn limits_outside_the_enforceable_range_are_rejected() {
+        let limits = |max_runtime_secs, max_memory_mb| ResourceLimits {
+            max_runtime_secs,
+            max_memory_mb,
+            ..ResourceLimits::default()
+        };
+
+        assert!(validate_limits(&limits(None, None)).is_ok());
+        assert!(validate_limits(&limits(Some(60), Some(MAX_MEMORY_LIMIT_MB))).is_ok());
+        assert!(validate_limits(&limits(Some(0), None)).is_err());
+        assert!(validate_limits(&limits(None, Some(0))).is_err());
+        assert!(validate_limits(&limits(None, Some(u64::MAX))).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::constants::SCRIPTS_DB_VERSION;
+use super::events::emit;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    // A file operation failed, `context` says which one
+    Io { context: String, source: io::Error },
+    Serialize(serde_json::Error),
+    // The database could not be read, what was left of it was recovered
+    CorruptDatabase { backup: PathBuf, message: String },
+    // The database was written by a newer build with a layout of this version
+    NewerDatabase(u64),
+    AlreadyExists(String),
+    NotFound(String),
+    InvalidPath(String),
+    // A resource limit that is zero or too large to enforce
+    InvalidLimits(String),
+    Secret(String),
+    // The file differs from the hash recorded when it was registered
+    ContentChanged { name: String, path: String },
+}
+
+pub type ScriptResult<T> = Result<T, ScriptError>;
+
+impl ScriptError {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        ScriptError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScriptError::Io { .. } => "io",
+            ScriptError::Serialize(_) => "serialize",
+            ScriptError::CorruptDatabase { .. } => "corrupt_database",
+            ScriptError::NewerDatabase(_) => "newer_database",
+            ScriptError::AlreadyExists(_) => "already_exists",
+            ScriptError::NotFound(_) => "not_found",
+            ScriptError::InvalidPath(_) => "invalid_path",
+            ScriptError::InvalidLimits(_) => "invalid_limits",
+            ScriptError::Secret(_) => "secret",
+            ScriptError::ContentChanged { .. } => "content_changed",
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io { context, source } => write!(f, "{}: {}", context, source),
+            ScriptError::Serialize(e) => write!(f, "Failed to serialize scripts: {}", e),
+            ScriptError::CorruptDatabase { backup, message } => write!(
+                f,
+                "Script database was corrupt ({}), a copy was saved to {}",
+                message,
+                backup.display()
+            ),
+            ScriptError::NewerDatabase(version) => write!(
+                f,
+                "Script database version {} is newer than this app supports ({})",
+                version, SCRIPTS_DB_VERSION
+            ),
+            ScriptError::AlreadyExists(name) => write!(f, "Script {} already exists", name),
+            ScriptError::NotFound(name) => write!(f, "Script {} not found", name),
+            ScriptError::InvalidPath(path) => write!(f, "Invalid script path: {}", path),
+            ScriptError::InvalidLimits(message) => write!(f, "Invalid limits: {}", message),
+            ScriptError::Secret(message) => write!(f, "Failed to store secret: {}", message),
+            ScriptError::ContentChanged { name, path } => write!(
+                f,
+                "Script {} changed since it was registered: {}",
+                name, path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScriptError::Io { source, .. } => Some(source),
+            ScriptError::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ScriptError {
+    fn from(e: serde_json::Error) -> Self {
+        ScriptError::Serialize(e)
+    }
+}
+
+// Lets the io::Result based run code use the database functions with `?`
+impl From<ScriptError> for io::Error {
+    fn from(e: ScriptError) -> Self {
+        let kind = match &e {
+            ScriptError::Io { source, .. } => source.kind(),
+            ScriptError::AlreadyExists(_) => io::ErrorKind::AlreadyExists,
+            ScriptError::NotFound(_) => io::ErrorKind::NotFound,
+            ScriptError::InvalidPath(_) | ScriptError::InvalidLimits(_) => {
+                io::ErrorKind::InvalidInput
+            }
+            _ => io::ErrorKind::Other,
+        };
+        // The error itself is kept so callers can downcast it
+        io::Error::new(kind, e)
+    }
+}
+
+pub fn path_context(action: &str, path: &Path) -> String {
+    format!("Failed to {} {}", action, path.display())
+}
+
+// Logs the error and tells the window about it
+pub fn report_error(error: &ScriptError) {
+    println!("Script Error - {}", error);
+    emit(
+        "scripts_error",
+        serde_json::json!({
+            "kind": error.kind(),
+            "message": error.to_string()
+        }),
+    );
+}
@@ -0,0 +1,20 @@
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+
+// Forwards script events to whoever listens, the window in the app
+pub type ScriptEmitter = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
+lazy_static! {
+    static ref EMITTER: Mutex<Option<ScriptEmitter>> = Mutex::new(None);
+}
+
+pub fn set_emitter(emitter: ScriptEmitter) {
+    *EMITTER.lock().unwrap() = Some(emitter);
+}
+
+pub fn emit(event: &str, payload: serde_json::Value) {
+    let emitter = EMITTER.lock().unwrap().clone();
+    if let Some(emitter) = emitter {
+        emitter(event, payload);
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+#[cfg(windows)]
+use std::sync::Mutex;
+
+use super::structs::{Interpreter, Priority, ResourceLimits};
+
+// Everything needed to launch a script, independent of the platform
+#[derive(Debug, Clone)]
+pub struct ExecutionRequest {
+    pub path: PathBuf,
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    pub env: HashMap<String, String>,
+    pub hidden: bool,
+    pub interpreter: Interpreter,
+    pub limits: ResourceLimits,
+}
+
+pub trait ScriptExecutor {
+    // Starts the script as a child of this process so its PID is known
+    fn spawn(&self, request: &ExecutionRequest) -> io::Result<Child>;
+    // Asks the process tree to exit
+    fn terminate(&self, pid: u32) -> io::Result<()>;
+    // Forcefully ends the process tree
+    fn kill(&self, pid: u32) -> io::Result<()>;
+}
+
+// Picks the interpreter for `Auto` from the script's extension
+fn resolve_interpreter(request: &ExecutionRequest) -> Interpreter {
+    if request.interpreter != Interpreter::Auto {
+        return request.interpreter;
+    }
+
+    let extension = request
+        .path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "ps1" => Interpreter::PowerShell,
+        "py" | "pyw" => Interpreter::Python,
+        "sh" | "bash" => Interpreter::Bash,
+        "js" | "mjs" | "cjs" => Interpreter::Node,
+        "bat" | "cmd" => Interpreter::Cmd,
+        _ => Interpreter::Direct,
+    }
+}
+
+// The program to start and the arguments that go before the script path
+fn interpreter_program(interpreter: Interpreter) -> Option<(&'static str, Vec<&'static str>)> {
+    match interpreter {
+        Interpreter::Auto | Interpreter::Direct => None,
+        Interpreter::Cmd => Some(("cmd", vec!["/C"])),
+        Interpreter::PowerShell => {
+            let program = if cfg!(windows) {
+                "powershell.exe"
+            } else {
+                "pwsh"
+            };
+            Some((
+                program,
+                vec!["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"],
+            ))
+        }
+        Interpreter::Bash => Some(("bash", vec![])),
+        Interpreter::Python => Some((if cfg!(windows) { "python" } else { "python3" }, vec![])),
+        Interpreter::Node => Some(("node", vec![])),
+    }
+}
+
+fn build_command(request: &ExecutionRequest) -> Command {
+    let mut cmd = match interpreter_program(resolve_interpreter(request)) {
+        Some((program, prefix)) => {
+            let mut cmd = Command::new(program);
+            cmd.args(prefix).arg(&request.path);
+            cmd
+        }
+        None => Command::new(&request.path),
+    };
+
+    cmd.args(&request.args)
+        .current_dir(&request.working_dir)
+        .envs(&request.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    cmd
+}
+
+#[cfg(windows)]
+pub struct WindowsExecutor;
+
+// A process has one console at a time, so only one stop can borrow a script's console
+#[cfg(windows)]
+static CONSOLE_LOCK: Mutex<()> = Mutex::new(());
+
+#[cfg(windows)]
+fn priority_class(priority: Priority) -> u32 {
+    use winapi::um::winbase::{
+        ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS,
+        IDLE_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+    };
+
+    match priority {
+        Priority::Idle => IDLE_PRIORITY_CLASS,
+        Priority::BelowNormal => BELOW_NORMAL_PRIORITY_CLASS,
+        Priority::Normal => NORMAL_PRIORITY_CLASS,
+        Priority::AboveNormal => ABOVE_NORMAL_PRIORITY_CLASS,
+        Priority::High => HIGH_PRIORITY_CLASS,
+    }
+}
+
+// Puts the script in a job object so the priority class also covers the processes it
+// starts. The memory limit is left to the watchdog in process.rs, a job memory cap would
+// only make allocations fail and the run would never be killed with the "memory" reason.
+// The job is assigned right after the spawn, a process the script starts before that
+// keeps the normal priority.
+#[cfg(windows)]
+fn apply_job_limits(child: &Child, limits: &ResourceLimits) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::jobapi2::{
+        AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject,
+    };
+    use winapi::um::winnt::{
+        JobObjectExtendedLimitInformation, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_PRIORITY_CLASS,
+    };
+
+    if limits.priority == Priority::Normal {
+        return Ok(());
+    }
+
+    let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+    info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_PRIORITY_CLASS;
+    info.BasicLimitInformation.PriorityClass = priority_class(limits.priority);
+
+    unsafe {
+        let job = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+        if job.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let applied = SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &mut info as *mut _ as *mut _,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        ) != 0
+            && AssignProcessToJobObject(job, child.as_raw_handle() as _) != 0;
+        let result = if applied {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        };
+
+        // The job lives on as long as a process is assigned to it
+        CloseHandle(job);
+        result
+    }
+}
+
+#[cfg(windows)]
+impl WindowsExecutor {
+    fn taskkill(pid: u32, force: bool) -> io::Result<()> {
+        use std::os::windows::process::CommandExt;
+
+        let mut cmd = Command::new("taskkill");
+        cmd.arg("/PID").arg(pid.to_string()).arg("/T");
+        if force {
+            cmd.arg("/F");
+        }
+        cmd.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
+
+        let output = cmd.output()?;
+        println!(
+            "Service Stop - {}",
+            String::from_utf8_lossy(&output.stdout).trim()
+        );
+
+        Ok(())
+    }
+
+    // Console events only reach processes attached to the sender's console, so this
+    // attaches to the script's console just long enough to send Ctrl+Break. The script
+    // leads its own process group, so the event skips this process.
+    fn ctrl_break(pid: u32) -> io::Result<()> {
+        use winapi::um::wincon::{
+            AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, GetConsoleWindow,
+            ATTACH_PARENT_PROCESS, CTRL_BREAK_EVENT,
+        };
+
+        let _guard = CONSOLE_LOCK.lock().unwrap();
+        unsafe {
+            let had_console = !GetConsoleWindow().is_null();
+            FreeConsole();
+
+            let result = if AttachConsole(pid) == 0
+                || GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) == 0
+            {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            };
+
+            FreeConsole();
+            // Gives a console started from a terminal its output back
+            if had_console {
+                AttachConsole(ATTACH_PARENT_PROCESS);
+            }
+
+            result
+        }
+    }
+}
+
+#[cfg(windows)]
+impl ScriptExecutor for WindowsExecutor {
+    fn spawn(&self, request: &ExecutionRequest) -> io::Result<Child> {
+        use std::os::windows::process::CommandExt;
+        use winapi::um::winbase::{CREATE_NEW_CONSOLE, CREATE_NEW_PROCESS_GROUP, CREATE_NO_WINDOW};
+
+        let mut cmd = build_command(request);
+
+        // Visible scripts get their own console window, hidden ones none at all
+        let window_flag = if request.hidden {
+            CREATE_NO_WINDOW
+        } else {
+            CREATE_NEW_CONSOLE
+        };
+        cmd.creation_flags(window_flag | CREATE_NEW_PROCESS_GROUP);
+
+        let child = cmd.spawn()?;
+        if let Err(e) = apply_job_limits(&child, &request.limits) {
+            println!("Service Limits Failed - pid {} ({})", child.id(), e);
+        }
+
+        Ok(child)
+    }
+
+    fn terminate(&self, pid: u32) -> io::Result<()> {
+        // Without a console to reach, asking the windows of the tree to close is all that is left
+        Self::ctrl_break(pid).or_else(|e| {
+            println!("Service Stop - Ctrl+Break failed for {}: {}", pid, e);
+            Self::taskkill(pid, false)
+        })
+    }
+
+    fn kill(&self, pid: u32) -> io::Result<()> {
+        Self::taskkill(pid, true)
+    }
+}
+
+#[cfg(unix)]
+pub struct UnixExecutor;
+
+#[cfg(unix)]
+fn nice_value(priority: Priority) -> libc::c_int {
+    match priority {
+        Priority::Idle => 19,
+        Priority::BelowNormal => 10,
+        Priority::Normal => 0,
+        Priority::AboveNormal => -5,
+        Priority::High => -10,
+    }
+}
+
+#[cfg(unix)]
+impl UnixExecutor {
+    fn signal_group(pid: u32, signal: libc::c_int) -> io::Result<()> {
+        // The script leads its own process group, a negative PID signals the whole group
+        let result = unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl ScriptExecutor for UnixExecutor {
+    fn spawn(&self, request: &ExecutionRequest) -> io::Result<Child> {
+        use std::os::unix::process::CommandExt;
+
+        let mut cmd = build_command(request);
+        cmd.process_group(0);
+
+        let nice = nice_value(request.limits.priority);
+        if nice != 0 {
+            // Runs in the child before exec, raising the priority needs privileges and
+            // the script keeps the normal one without them
+            unsafe {
+                cmd.pre_exec(move || {
+                    libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                    Ok(())
+                });
+            }
+        }
+
+        cmd.spawn()
+    }
+
+    fn terminate(&self, pid: u32) -> io::Result<()> {
+        Self::signal_group(pid, libc::SIGTERM)
+    }
+
+    fn kill(&self, pid: u32) -> io::Result<()> {
+        Self::signal_group(pid, libc::SIGKILL)
+    }
+}
+
+pub fn default_executor() -> Box<dyn ScriptExecutor> {
+    #[cfg(windows)]
+    {
+        Box::new(WindowsExecutor)
+    }
+    #[cfg(unix)]
+    {
+        Box::new(UnixExecutor)
+    }
+}
@@ -0,0 +1,132 @@
+use lazy_static::lazy_static;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::constants::{MAX_HISTORY_AGE_DAYS, MAX_HISTORY_PER_SCRIPT, SCRIPTS_HISTORY_DB};
+use super::disk::{get_scripts_folder_path, write_atomically};
+use super::process::now_millis;
+use super::structs::RunRecord;
+
+lazy_static! {
+    // Runs finish on their own threads, serialize the read-modify-write of the history file
+    static ref HISTORY_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn read_history() -> Vec<RunRecord> {
+    let path = get_scripts_folder_path().join(format!("{0}.json", SCRIPTS_HISTORY_DB));
+    if !path.exists() {
+        return vec![];
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            println!("Script history could not be parsed: {}", e);
+            vec![]
+        }),
+        Err(e) => {
+            println!("Script history could not be read: {}", e);
+            vec![]
+        }
+    }
+}
+
+fn write_history(records: &[RunRecord]) -> io::Result<()> {
+    let path = get_scripts_folder_path().join(format!("{0}.json", SCRIPTS_HISTORY_DB));
+    let data = serde_json::to_string_pretty(records)?;
+    write_atomically(&path, data.as_bytes())
+}
+
+// Drops finished runs past the age limit or beyond the per script limit, with their logs
+fn apply_retention(records: &mut Vec<RunRecord>) {
+    let max_age = MAX_HISTORY_AGE_DAYS * 24 * 60 * 60 * 1000;
+    let now = now_millis();
+    let mut kept_per_script: std::collections::HashMap<String, usize> = Default::default();
+
+    // Newest first so the per script count keeps the latest runs
+    records.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    records.retain(|record| {
+        let count = kept_per_script
+            .entry(record.script_name.clone())
+            .or_insert(0);
+        let finished = record.ended_at.is_some();
+        let expired = now.saturating_sub(record.started_at) > max_age;
+
+        if finished && (expired || *count >= MAX_HISTORY_PER_SCRIPT) {
+            let log_path = Path::new(&record.log_path);
+            if log_path.exists() {
+                let _ = fs::remove_file(log_path);
+            }
+            return false;
+        }
+
+        *count += 1;
+        true
+    });
+}
+
+pub fn record_run_started(record: RunRecord) -> io::Result<()> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut records = read_history();
+    records.push(record);
+    apply_retention(&mut records);
+    write_history(&records)
+}
+
+pub fn record_run_finished(
+    run_id: &str,
+    exit_code: Option<i32>,
+    stop_reason: Option<String>,
+) -> io::Result<()> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut records = read_history();
+
+    if let Some(record) = records.iter_mut().find(|record| record.run_id == run_id) {
+        record.ended_at = Some(now_millis());
+        record.exit_code = exit_code;
+        record.stop_reason = stop_reason;
+    }
+
+    write_history(&records)
+}
+
+// Runs of one script, or of every script, newest first
+pub fn get_history(script_name: Option<&str>) -> Vec<RunRecord> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    let mut records = read_history();
+    if let Some(script_name) = script_name {
+        records.retain(|record| record.script_name == script_name);
+    }
+    records.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+
+    records
+}
+
+pub fn get_history_entry(run_id: &str) -> Option<RunRecord> {
+    let _lock = HISTORY_LOCK.lock().unwrap();
+    read_history()
+        .into_iter()
+        .find(|record| record.run_id == run_id)
+}
+
+// Log lines of a run starting at `from`, with the index to continue from,
+// so callers can follow a run that is still writing
+pub fn read_run_log(run_id: &str, from: usize) -> io::Result<(Vec<String>, usize)> {
+    let record = get_history_entry(run_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Run {} not found in history", run_id),
+        )
+    })?;
+
+    let bytes = fs::read(&record.log_path)?;
+    let lines = String::from_utf8_lossy(&bytes)
+        .lines()
+        .skip(from)
+        .map(|line| line.to_string())
+        .collect::<Vec<String>>();
+    let next = from + lines.len();
+
+    Ok((lines, next))
+}
@@ -0,0 +1,131 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use super::disk::{find_script, list_scripts, set_content_hash};
+use super::error::{path_context, report_error, ScriptError, ScriptResult};
+use super::events;
+use super::structs::ScriptSave;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentState {
+    Unchanged,
+    Changed,
+    // Registered before hashes were stored
+    Unregistered,
+    Missing,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub(crate) name: String,
+    pub(crate) path: String,
+    pub(crate) content_hash: Option<String>,
+    pub(crate) current_hash: Option<String>,
+    pub(crate) state: ContentState,
+    pub(crate) source_path: Option<String>,
+    // The original file compared with the hash of the copy, None for scripts that were not copied
+    pub(crate) source_state: Option<ContentState>,
+}
+
+pub fn hash_file(path: &Path) -> ScriptResult<String> {
+    let mut file = File::open(path).map_err(|e| ScriptError::io(path_context("open", path), e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| ScriptError::io(path_context("read", path), e))?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+fn compare(path: &str, content_hash: Option<&String>) -> (Option<String>, ContentState) {
+    let Ok(current_hash) = hash_file(Path::new(path)) else {
+        return (None, ContentState::Missing);
+    };
+
+    let state = match content_hash {
+        None => ContentState::Unregistered,
+        Some(hash) if *hash == current_hash => ContentState::Unchanged,
+        Some(_) => ContentState::Changed,
+    };
+
+    (Some(current_hash), state)
+}
+
+pub fn check_script(script: &ScriptSave) -> IntegrityReport {
+    let content_hash = script.integrity.content_hash.as_ref();
+    let (current_hash, state) = compare(&script.path, content_hash);
+    let source_state = script
+        .integrity
+        .source_path
+        .as_ref()
+        .map(|source_path| compare(source_path, content_hash).1);
+
+    IntegrityReport {
+        name: script.name.clone(),
+        path: script.path.clone(),
+        content_hash: script.integrity.content_hash.clone(),
+        current_hash,
+        state,
+        source_path: script.integrity.source_path.clone(),
+        source_state,
+    }
+}
+
+pub fn check_scripts() -> Vec<IntegrityReport> {
+    list_scripts().iter().map(check_script).collect()
+}
+
+// Runs before every start, a manual run or rerun of a changed file needs the user's confirmation.
+// API runs come from another machine with only the shared token, so they are refused until the
+// change is accepted, while local unattended triggers only warn since nobody is there to confirm
+pub fn verify_before_run(
+    name: Option<&str>,
+    path: &str,
+    allow_changed: bool,
+    trigger: &str,
+) -> ScriptResult<()> {
+    let Some(script) = find_script(name, path) else {
+        return Ok(());
+    };
+
+    let report = check_script(&script);
+    match (report.state, report.current_hash) {
+        (ContentState::Unregistered, Some(current_hash)) => {
+            if let Err(e) = set_content_hash(&script.name, current_hash) {
+                report_error(&e);
+            }
+            Ok(())
+        }
+        (ContentState::Changed, _) => {
+            events::emit(
+                "script_integrity_warning",
+                serde_json::json!({
+                    "name": script.name,
+                    "path": script.path,
+                    "trigger": trigger,
+                }),
+            );
+
+            let needs_approval = matches!(trigger, "manual" | "rerun" | "api");
+            if allow_changed || !needs_approval {
+                println!(
+                    "Integrity Warning - {} changed since it was registered, started by {}",
+                    script.path, trigger
+                );
+                return Ok(());
+            }
+
+            Err(ScriptError::ContentChanged {
+                name: script.name,
+                path: script.path,
+            })
+        }
+        _ => Ok(()),
+    }
+}
@@ -1,4 +1,14 @@
+pub(crate) mod arguments;
 pub(crate) mod constants;
 pub(crate) mod disk;
+pub(crate) mod error;
+pub(crate) mod events;
+pub(crate) mod executor;
+pub(crate) mod history;
+pub(crate) mod integrity;
+pub(crate) mod pipelines;
+pub(crate) mod process;
+pub(crate) mod scheduler;
 pub(crate) mod structs;
 pub(crate) mod terminal;
+pub(crate) mod watch;
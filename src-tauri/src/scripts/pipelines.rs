@@ -0,0 +1,463 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use super::constants::{MAX_FINISHED_PIPELINE_RUNS, SCRIPTS_PIPELINES_DB};
+use super::disk::{find_script, get_scripts_folder_path, write_atomically};
+use super::events::emit;
+use super::history::get_history_entry;
+use super::integrity::verify_before_run;
+use super::process::{now_millis, wait_for_run};
+use super::terminal::{saved_script, start_script_with_trigger};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineMode {
+    #[default]
+    Sequential,
+    Parallel,
+}
+
+// What a step receives from the step before it, appended as the last argument
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassPrevious {
+    #[default]
+    Nothing,
+    // Everything the previous step wrote to stdout, trimmed
+    Output,
+    ExitCode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStep {
+    pub(crate) script_name: String,
+    #[serde(default)]
+    pub(crate) arguments: String,
+    #[serde(default)]
+    pub(crate) values: Option<Vec<Option<String>>>,
+    #[serde(default)]
+    pub(crate) continue_on_error: bool,
+    #[serde(default)]
+    pub(crate) timeout_secs: Option<u64>,
+    // Extra attempts after a failed one
+    #[serde(default)]
+    pub(crate) retries: u32,
+    // Ignored in parallel pipelines, there is no previous step there
+    #[serde(default)]
+    pub(crate) pass_previous: PassPrevious,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pipeline {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) mode: PipelineMode,
+    #[serde(default = "default_hidden")]
+    pub(crate) hidden: bool,
+    pub(crate) steps: Vec<PipelineStep>,
+}
+
+fn default_hidden() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    pub(crate) script_name: String,
+    // One run per attempt
+    pub(crate) run_ids: Vec<String>,
+    pub(crate) status: String,
+    pub(crate) exit_code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineRun {
+    pub(crate) pipeline_run_id: String,
+    pub(crate) pipeline: String,
+    pub(crate) trigger: String,
+    pub(crate) status: String,
+    pub(crate) started_at: u64,
+    pub(crate) ended_at: Option<u64>,
+    pub(crate) steps: Vec<StepResult>,
+}
+
+lazy_static! {
+    static ref PIPELINE_RUNS: Mutex<HashMap<String, PipelineRun>> = Mutex::new(HashMap::new());
+    // Held from reading the pipelines file until the changed list is written back
+    static ref PIPELINES_LOCK: Mutex<()> = Mutex::new(());
+}
+
+static PIPELINE_RUN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn read_pipelines() -> Vec<Pipeline> {
+    let path = get_scripts_folder_path().join(format!("{0}.json", SCRIPTS_PIPELINES_DB));
+    if !path.exists() {
+        return vec![];
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            println!("Script pipelines could not be parsed: {}", e);
+            vec![]
+        }),
+        Err(e) => {
+            println!("Script pipelines could not be read: {}", e);
+            vec![]
+        }
+    }
+}
+
+fn write_pipelines(pipelines: &[Pipeline]) -> io::Result<()> {
+    let path = get_scripts_folder_path().join(format!("{0}.json", SCRIPTS_PIPELINES_DB));
+    let data = serde_json::to_string_pretty(pipelines)?;
+    write_atomically(&path, data.as_bytes())
+}
+
+pub fn list_pipelines() -> Vec<Pipeline> {
+    read_pipelines()
+}
+
+pub fn find_pipeline(name: &str) -> Option<Pipeline> {
+    read_pipelines()
+        .into_iter()
+        .find(|pipeline| pipeline.name == name)
+}
+
+// Adds the pipeline or replaces the one with the same name
+pub fn save_pipeline(pipeline: Pipeline) -> io::Result<()> {
+    if pipeline.name.is_empty() || pipeline.steps.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "A pipeline needs a name and at least one step",
+        ));
+    }
+
+    if let Some(step) = pipeline
+        .steps
+        .iter()
+        .find(|step| find_script(Some(&step.script_name), "").is_none())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Script {} not found", step.script_name),
+        ));
+    }
+
+    let _lock = PIPELINES_LOCK.lock().unwrap();
+    let mut pipelines = read_pipelines();
+    match pipelines
+        .iter_mut()
+        .find(|existing| existing.name == pipeline.name)
+    {
+        Some(existing) => *existing = pipeline,
+        None => pipelines.push(pipeline),
+    }
+
+    write_pipelines(&pipelines)
+}
+
+pub fn remove_pipeline(name: &str) -> io::Result<bool> {
+    let _lock = PIPELINES_LOCK.lock().unwrap();
+    let mut pipelines = read_pipelines();
+    let count = pipelines.len();
+    pipelines.retain(|pipeline| pipeline.name != name);
+
+    if pipelines.len() == count {
+        return Ok(false);
+    }
+    write_pipelines(&pipelines)?;
+
+    Ok(true)
+}
+
+pub fn get_pipeline_run(pipeline_run_id: &str) -> Option<PipelineRun> {
+    PIPELINE_RUNS.lock().unwrap().get(pipeline_run_id).cloned()
+}
+
+fn update_run<F: FnOnce(&mut PipelineRun)>(pipeline_run_id: &str, update: F) {
+    let snapshot = {
+        let mut runs = PIPELINE_RUNS.lock().unwrap();
+        let Some(run) = runs.get_mut(pipeline_run_id) else {
+            return;
+        };
+        update(run);
+        run.clone()
+    };
+
+    emit(
+        "pipeline_status",
+        serde_json::to_value(snapshot).unwrap_or_default(),
+    );
+}
+
+// Stdout lines of a finished run, read back from its log
+fn read_run_output(run_id: &str) -> String {
+    let Some(record) = get_history_entry(run_id) else {
+        return String::new();
+    };
+
+    fs::read(&record.log_path)
+        .map(|bytes| {
+            String::from_utf8_lossy(&bytes)
+                .lines()
+                .filter_map(|line| line.strip_prefix("[stdout] "))
+                .collect::<Vec<&str>>()
+                .join("\n")
+                .trim()
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+// Runs one step with its retries, `input` is appended as its last argument
+fn run_step(
+    pipeline_run_id: &str,
+    index: usize,
+    step: &PipelineStep,
+    hidden: bool,
+    input: Option<String>,
+) -> StepResult {
+    let mut result = StepResult {
+        script_name: step.script_name.clone(),
+        run_ids: vec![],
+        status: "failed".to_string(),
+        exit_code: None,
+    };
+
+    let mut script = match saved_script(
+        &step.script_name,
+        hidden,
+        step.arguments.clone(),
+        step.values.clone(),
+    ) {
+        Ok(script) => script,
+        Err(e) => {
+            println!("Pipeline Step Failed - {} ({})", pipeline_run_id, e);
+            return result;
+        }
+    };
+    script.extra_args = input.into_iter().collect();
+
+    for attempt in 0..=step.retries {
+        if attempt > 0 {
+            println!(
+                "Pipeline Step Retry - {} step {} attempt {}",
+                pipeline_run_id,
+                index + 1,
+                attempt + 1
+            );
+        }
+
+        let run_id = match start_script_with_trigger(&script, "pipeline") {
+            Ok(run_id) => run_id,
+            Err(e) => {
+                println!("Pipeline Step Failed - {} ({})", pipeline_run_id, e);
+                continue;
+            }
+        };
+        result.run_ids.push(run_id.clone());
+        update_run(pipeline_run_id, |run| {
+            run.steps[index] = StepResult {
+                status: "running".to_string(),
+                ..result.clone()
+            };
+        });
+
+        let outcome = match wait_for_run(&run_id, step.timeout_secs.map(Duration::from_secs)) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                println!("Pipeline Step Failed - {} ({})", pipeline_run_id, e);
+                continue;
+            }
+        };
+        result.exit_code = outcome.exit_code;
+
+        if outcome.timed_out {
+            result.status = "timed_out".to_string();
+            continue;
+        }
+        if outcome.exit_code == Some(0) {
+            result.status = "succeeded".to_string();
+            return result;
+        }
+        result.status = "failed".to_string();
+    }
+
+    result
+}
+
+// What the step asked to receive from the result of the step before it
+fn step_input(step: &PipelineStep, previous: Option<&StepResult>) -> Option<String> {
+    let previous = previous?;
+    match step.pass_previous {
+        PassPrevious::Nothing => None,
+        PassPrevious::Output => previous
+            .run_ids
+            .last()
+            .map(|run_id| read_run_output(run_id)),
+        PassPrevious::ExitCode => Some(
+            previous
+                .exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "-1".to_string()),
+        ),
+    }
+}
+
+fn execute(pipeline: &Pipeline, pipeline_run_id: &str) -> bool {
+    let succeeded = match pipeline.mode {
+        PipelineMode::Sequential => {
+            let mut previous: Option<StepResult> = None;
+            let mut succeeded = true;
+            for (index, step) in pipeline.steps.iter().enumerate() {
+                let input = step_input(step, previous.as_ref());
+                let result = run_step(pipeline_run_id, index, step, pipeline.hidden, input);
+                let failed = result.status != "succeeded";
+                update_run(pipeline_run_id, |run| run.steps[index] = result.clone());
+                previous = Some(result);
+
+                // Failures of steps that continue on error do not fail the pipeline
+                if failed && !step.continue_on_error {
+                    succeeded = false;
+                    break;
+                }
+            }
+            succeeded
+        }
+        PipelineMode::Parallel => {
+            let handles = pipeline
+                .steps
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(index, step)| {
+                    let pipeline_run_id = pipeline_run_id.to_string();
+                    let hidden = pipeline.hidden;
+                    thread::spawn(move || {
+                        let result = run_step(&pipeline_run_id, index, &step, hidden, None);
+                        let failed = result.status != "succeeded";
+                        update_run(&pipeline_run_id, |run| run.steps[index] = result);
+                        !failed || step.continue_on_error
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or(false))
+                .fold(true, |succeeded, step| succeeded && step)
+        }
+    };
+
+    update_run(pipeline_run_id, |run| {
+        run.status = if succeeded { "succeeded" } else { "failed" }.to_string();
+        run.ended_at = Some(now_millis());
+    });
+
+    succeeded
+}
+
+// Keeps the newest finished runs, running ones are never dropped
+fn prune_finished_runs(runs: &mut HashMap<String, PipelineRun>) {
+    let mut finished = runs
+        .values()
+        .filter_map(|run| {
+            run.ended_at
+                .map(|ended_at| (ended_at, run.pipeline_run_id.clone()))
+        })
+        .collect::<Vec<(u64, String)>>();
+    if finished.len() <= MAX_FINISHED_PIPELINE_RUNS {
+        return;
+    }
+
+    finished.sort();
+    let excess = finished.len() - MAX_FINISHED_PIPELINE_RUNS;
+    for (_, pipeline_run_id) in finished.into_iter().take(excess) {
+        runs.remove(&pipeline_run_id);
+    }
+}
+
+fn register_run(pipeline: &Pipeline, trigger: &str) -> String {
+    let pipeline_run_id = format!(
+        "pipeline-{}-{}",
+        now_millis(),
+        PIPELINE_RUN_COUNTER.fetch_add(1, Ordering::SeqCst)
+    );
+
+    let steps = pipeline
+        .steps
+        .iter()
+        .map(|step| StepResult {
+            script_name: step.script_name.clone(),
+            run_ids: vec![],
+            status: "pending".to_string(),
+            exit_code: None,
+        })
+        .collect();
+
+    let mut runs = PIPELINE_RUNS.lock().unwrap();
+    prune_finished_runs(&mut runs);
+    runs.insert(
+        pipeline_run_id.clone(),
+        PipelineRun {
+            pipeline_run_id: pipeline_run_id.clone(),
+            pipeline: pipeline.name.clone(),
+            trigger: trigger.to_string(),
+            status: "running".to_string(),
+            started_at: now_millis(),
+            ended_at: None,
+            steps,
+        },
+    );
+
+    pipeline_run_id
+}
+
+fn get_pipeline(name: &str) -> io::Result<Pipeline> {
+    find_pipeline(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Pipeline {} not found", name),
+        )
+    })
+}
+
+// Starts the pipeline on its own thread and returns the id to follow it with
+pub fn start_pipeline(name: &str, trigger: &str) -> io::Result<String> {
+    let pipeline = get_pipeline(name)?;
+    // The steps run with the pipeline trigger, so a changed script is refused up front
+    if trigger == "api" {
+        for step in &pipeline.steps {
+            verify_before_run(Some(&step.script_name), "", false, trigger)?;
+        }
+    }
+    let pipeline_run_id = register_run(&pipeline, trigger);
+
+    let thread_run_id = pipeline_run_id.clone();
+    thread::spawn(move || {
+        execute(&pipeline, &thread_run_id);
+    });
+
+    Ok(pipeline_run_id)
+}
+
+// Runs the pipeline on the calling thread, used by the command line
+pub fn run_pipeline_blocking(name: &str, trigger: &str) -> io::Result<PipelineRun> {
+    let pipeline = get_pipeline(name)?;
+    let pipeline_run_id = register_run(&pipeline, trigger);
+    execute(&pipeline, &pipeline_run_id);
+
+    get_pipeline_run(&pipeline_run_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Pipeline run {} not found", pipeline_run_id),
+        )
+    })
+}
@@ -0,0 +1,505 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sysinfo::{Pid, System};
+
+use super::constants::{LIMITS_TICK_MILLIS, STATUS_INTERVAL_SECS, STOP_TIMEOUT_SECS};
+use super::disk::{get_script_logs_folder_path, mark_script_used};
+use super::events::emit;
+use super::executor::{default_executor, ExecutionRequest};
+use super::history::{get_history_entry, record_run_finished, record_run_started};
+use super::structs::{ResourceLimits, RunRecord, RunningScript, Script};
+use crate::utils::secrets::{redact, resolve_secrets};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunInfo {
+    pub(crate) run_id: String,
+    pub(crate) script_name: String,
+    pub(crate) pid: u32,
+    pub(crate) path: String,
+    pub(crate) started_at: u64,
+    pub(crate) log_path: String,
+    // Set when we asked the run to stop, so its exit is reported as killed and the reason recorded
+    pub(crate) stop_reason: Option<String>,
+}
+
+lazy_static! {
+    // Runs started by this app that have not exited yet, keyed by run id
+    static ref RUNS: Mutex<HashMap<String, RunInfo>> = Mutex::new(HashMap::new());
+    // Kept between calls so CPU usage is measured against the previous refresh
+    static ref SYSTEM: Mutex<System> = Mutex::new(System::new());
+}
+
+static RUN_COUNTER: AtomicU64 = AtomicU64::new(0);
+static MONITOR_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn next_run_id() -> String {
+    let counter = RUN_COUNTER.fetch_add(1, Ordering::SeqCst);
+    format!("{:x}-{}", now_millis(), counter)
+}
+
+// Reads the stream line by line, decoding lossily so non UTF-8 output cannot fail the run,
+// and sends every line to the window and the run's log file
+fn capture_stream<R: Read + Send + 'static>(
+    reader: R,
+    stream: &'static str,
+    run_id: String,
+    log_file: Arc<Mutex<Option<File>>>,
+    secrets: Arc<Vec<String>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buffer = vec![];
+
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let line = redact(
+                        String::from_utf8_lossy(&buffer).trim_end_matches(['\r', '\n']),
+                        &secrets,
+                    );
+
+                    if let Some(file) = log_file.lock().unwrap().as_mut() {
+                        let _ = writeln!(file, "[{}] {}", stream, line);
+                    }
+
+                    emit(
+                        "script_output",
+                        serde_json::json!({
+                            "run_id": run_id,
+                            "stream": stream,
+                            "line": line
+                        }),
+                    );
+                }
+                Err(e) => {
+                    println!("Service Output Failed - run {} ({})", run_id, e);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+fn emit_status(
+    run_id: &str,
+    script_name: &str,
+    status: &str,
+    exit_code: Option<i32>,
+    reason: Option<&str>,
+) {
+    emit(
+        "script_status",
+        serde_json::json!({
+            "run_id": run_id,
+            "name": script_name,
+            "status": status,
+            "exit_code": exit_code,
+            "reason": reason
+        }),
+    );
+}
+
+// Sends a running heartbeat with resource usage for every live run until none are left
+fn ensure_status_monitor() {
+    if MONITOR_ACTIVE.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(|| loop {
+        thread::sleep(Duration::from_secs(STATUS_INTERVAL_SECS));
+
+        let running = list_running();
+        if running.is_empty() {
+            MONITOR_ACTIVE.store(false, Ordering::SeqCst);
+            // A run may have registered between the check and the store
+            if RUNS.lock().unwrap().is_empty() || MONITOR_ACTIVE.swap(true, Ordering::SeqCst) {
+                break;
+            }
+            continue;
+        }
+
+        for run in running {
+            emit(
+                "script_status",
+                serde_json::json!({
+                    "run_id": run.run_id,
+                    "name": run.script_name,
+                    "status": "running",
+                    "pid": run.pid,
+                    "uptime_secs": run.uptime_secs,
+                    "cpu_usage": run.cpu_usage,
+                    "memory_bytes": run.memory_bytes
+                }),
+            );
+        }
+    });
+}
+
+fn get_script_name(script: &Script) -> String {
+    script.name.clone().unwrap_or_else(|| {
+        std::path::Path::new(&script.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| script.path.clone())
+    })
+}
+
+// Swaps secret references in the arguments and environment for their values
+fn resolve_request(request: &ExecutionRequest) -> io::Result<(ExecutionRequest, Vec<String>)> {
+    let mut secrets = vec![];
+    let mut resolved = request.clone();
+    let to_io_error = |e: Box<dyn std::error::Error + Send + Sync>| {
+        io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+    };
+
+    resolved.args = request
+        .args
+        .iter()
+        .map(|arg| resolve_secrets(arg, &mut secrets))
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(to_io_error)?;
+
+    for value in resolved.env.values_mut() {
+        *value = resolve_secrets(value, &mut secrets).map_err(to_io_error)?;
+    }
+
+    Ok((resolved, secrets))
+}
+
+// Spawns the request and records the run in the history, `trigger` tells what started it
+pub fn start_run(request: &ExecutionRequest, script: &Script, trigger: &str) -> io::Result<String> {
+    // Printed before resolving so secrets stay as references in the console
+    println!(
+        "Service Command - {} {:?}",
+        request.path.display(),
+        request.args
+    );
+
+    let (resolved, secrets) = resolve_request(request)?;
+    let secrets = Arc::new(secrets);
+    let mut child = default_executor().spawn(&resolved)?;
+    let run_id = next_run_id();
+    let pid = child.id();
+
+    println!("Service Started - run {} pid {}", run_id, pid);
+
+    let log_path = get_script_logs_folder_path().join(format!("{}.log", run_id));
+    let log_file = match File::create(&log_path) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            println!("Service Log Failed - run {} ({})", run_id, e);
+            None
+        }
+    };
+    let log_file = Arc::new(Mutex::new(log_file));
+
+    let mut readers = vec![];
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(capture_stream(
+            stdout,
+            "stdout",
+            run_id.clone(),
+            log_file.clone(),
+            secrets.clone(),
+        ));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(capture_stream(
+            stderr,
+            "stderr",
+            run_id.clone(),
+            log_file.clone(),
+            secrets.clone(),
+        ));
+    }
+
+    let started_at = now_millis();
+    let script_name = get_script_name(script);
+    RUNS.lock().unwrap().insert(
+        run_id.clone(),
+        RunInfo {
+            run_id: run_id.clone(),
+            script_name: script_name.clone(),
+            pid,
+            path: script.path.clone(),
+            started_at,
+            log_path: log_path.to_string_lossy().to_string(),
+            stop_reason: None,
+        },
+    );
+
+    // Recorded before the waiter starts so the exit always finds its entry
+    if let Err(e) = record_run_started(RunRecord {
+        run_id: run_id.clone(),
+        script_name: script_name.clone(),
+        script: script.clone(),
+        args: request.args.clone(),
+        trigger: trigger.to_string(),
+        started_at,
+        ended_at: None,
+        exit_code: None,
+        log_path: log_path.to_string_lossy().to_string(),
+        stop_reason: None,
+    }) {
+        println!("Service History Failed - run {} ({})", run_id, e);
+    }
+
+    mark_script_used(script.name.as_deref(), &script.path, started_at);
+    emit_status(&run_id, &script_name, "started", None, None);
+    ensure_status_monitor();
+    watch_limits(run_id.clone(), pid, resolved.limits.clone());
+
+    // Wait for the child on its own thread, which also reaps it
+    let waiter_run_id = run_id.clone();
+    thread::spawn(move || {
+        let status = child.wait();
+
+        // Let the readers drain what the script wrote before it exited
+        for reader in readers {
+            let _ = reader.join();
+        }
+
+        let stop_reason = RUNS
+            .lock()
+            .unwrap()
+            .get(&waiter_run_id)
+            .and_then(|run| run.stop_reason.clone());
+        let exit_code = match status {
+            Ok(status) => {
+                println!("Service Exited - run {} ({})", waiter_run_id, status);
+                status.code()
+            }
+            Err(e) => {
+                println!("Service Wait Failed - run {} ({})", waiter_run_id, e);
+                None
+            }
+        };
+
+        if let Err(e) = record_run_finished(&waiter_run_id, exit_code, stop_reason.clone()) {
+            println!("Service History Failed - run {} ({})", waiter_run_id, e);
+        }
+
+        // Removed after the history has the exit code, so anyone waiting on the run can read it
+        RUNS.lock().unwrap().remove(&waiter_run_id);
+
+        // Without an exit code the process was ended by a signal
+        let status = if stop_reason.is_some() || exit_code.is_none() {
+            "killed"
+        } else {
+            "exited"
+        };
+        emit_status(
+            &waiter_run_id,
+            &script_name,
+            status,
+            exit_code,
+            stop_reason.as_deref(),
+        );
+    });
+
+    Ok(run_id)
+}
+
+pub fn is_running(run_id: &str) -> bool {
+    RUNS.lock().unwrap().contains_key(run_id)
+}
+
+pub fn get_run(run_id: &str) -> Option<RunInfo> {
+    RUNS.lock().unwrap().get(run_id).cloned()
+}
+
+// Live runs with their uptime and the CPU and memory usage reported by the OS
+pub fn list_running() -> Vec<RunningScript> {
+    let runs = RUNS
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect::<Vec<RunInfo>>();
+    if runs.is_empty() {
+        return vec![];
+    }
+
+    let mut system = SYSTEM.lock().unwrap();
+    system.refresh_processes();
+    let now = now_millis();
+
+    runs.into_iter()
+        .map(|run| {
+            let process = system.process(Pid::from_u32(run.pid));
+            RunningScript {
+                uptime_secs: now.saturating_sub(run.started_at) / 1000,
+                cpu_usage: process.map_or(0.0, |process| process.cpu_usage()),
+                memory_bytes: process.map_or(0, |process| process.memory()),
+                run_id: run.run_id,
+                script_name: run.script_name,
+                pid: run.pid,
+                path: run.path,
+                started_at: run.started_at,
+            }
+        })
+        .collect()
+}
+
+pub fn find_runs_by_path(path: &str) -> Vec<String> {
+    RUNS.lock()
+        .unwrap()
+        .values()
+        .filter(|run| run.path == path)
+        .map(|run| run.run_id.clone())
+        .collect()
+}
+
+// Memory of the process and everything it started, scripts often hand the work to a child
+fn tree_memory(pid: u32) -> u64 {
+    let mut system = SYSTEM.lock().unwrap();
+    system.refresh_processes();
+
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for (child_pid, process) in system.processes() {
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(*child_pid);
+        }
+    }
+
+    let mut tree = vec![Pid::from_u32(pid)];
+    let mut index = 0;
+    while index < tree.len() {
+        if let Some(pids) = children.get(&tree[index]) {
+            tree.extend(pids.iter().copied());
+        }
+        index += 1;
+    }
+
+    tree.iter()
+        .filter_map(|pid| system.process(*pid))
+        .map(|process| process.memory())
+        .sum()
+}
+
+// Ends the run once it goes over its time or memory limit, the reason ends up in the history
+fn watch_limits(run_id: String, pid: u32, limits: ResourceLimits) {
+    if limits.max_runtime_secs.is_none() && limits.max_memory_mb.is_none() {
+        return;
+    }
+
+    thread::spawn(move || {
+        let started = Instant::now();
+        let max_runtime = limits.max_runtime_secs.map(Duration::from_secs);
+        let max_memory = limits
+            .max_memory_mb
+            .map(|mb| mb.saturating_mul(1024 * 1024));
+
+        while is_running(&run_id) {
+            if max_runtime.map_or(false, |max_runtime| started.elapsed() >= max_runtime) {
+                println!("Service Limit - run {} went over its time limit", run_id);
+                if let Err(e) = stop_run_with_reason(&run_id, "timeout") {
+                    println!("Service Kill Failed - run {} ({})", run_id, e);
+                }
+                break;
+            }
+
+            if let Some(max_memory) = max_memory {
+                let used = tree_memory(pid);
+                if used > max_memory {
+                    println!(
+                        "Service Limit - run {} used {} bytes, limit {}",
+                        run_id, used, max_memory
+                    );
+                    mark_stopping(&run_id, "memory");
+                    if let Err(e) = default_executor().kill(pid) {
+                        println!("Service Kill Failed - run {} ({})", run_id, e);
+                    }
+                    break;
+                }
+            }
+
+            thread::sleep(Duration::from_millis(LIMITS_TICK_MILLIS));
+        }
+    });
+}
+
+// The first reason wins, stopping a run that is already timing out keeps it a timeout
+fn mark_stopping(run_id: &str, reason: &str) {
+    if let Some(run) = RUNS.lock().unwrap().get_mut(run_id) {
+        run.stop_reason.get_or_insert_with(|| reason.to_string());
+    }
+}
+
+pub fn stop_run(run_id: &str) -> io::Result<bool> {
+    stop_run_with_reason(run_id, "stopped")
+}
+
+// Asks the run's process tree to exit and kills it when it is still alive after the timeout
+fn stop_run_with_reason(run_id: &str, reason: &str) -> io::Result<bool> {
+    let Some(run) = get_run(run_id) else {
+        return Ok(false);
+    };
+
+    mark_stopping(run_id, reason);
+
+    let executor = default_executor();
+    if let Err(e) = executor.terminate(run.pid) {
+        println!("Service Terminate Failed - run {} ({})", run_id, e);
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(STOP_TIMEOUT_SECS);
+    while Instant::now() < deadline {
+        if !is_running(run_id) {
+            return Ok(true);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    println!("Service Kill - run {} did not exit in time", run_id);
+    executor.kill(run.pid)?;
+
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunOutcome {
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) timed_out: bool,
+}
+
+// Blocks until the run ends and returns its exit code, a run still going after
+// the timeout is stopped and reported as timed out
+pub fn wait_for_run(run_id: &str, timeout: Option<Duration>) -> io::Result<RunOutcome> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut timed_out = false;
+
+    while is_running(run_id) {
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            println!("Service Timeout - run {}", run_id);
+            timed_out = true;
+            stop_run_with_reason(run_id, "timeout")?;
+            while is_running(run_id) {
+                thread::sleep(Duration::from_millis(100));
+            }
+            break;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    Ok(RunOutcome {
+        exit_code: get_history_entry(run_id).and_then(|record| record.exit_code),
+        timed_out,
+    })
+}
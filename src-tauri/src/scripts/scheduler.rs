@@ -0,0 +1,486 @@
+use chrono::{DateTime, Local, TimeZone};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::constants::{
+    MAX_INTERVAL_SECS, MAX_MISSED_RUNS, SCHEDULER_TICK_SECS, SCRIPTS_SCHEDULES_DB,
+};
+use super::disk::{find_script, get_scripts_folder_path};
+use super::events::emit;
+use super::process::{find_runs_by_path, is_running, now_millis};
+use super::terminal::{saved_script, start_script_with_trigger};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleKind {
+    // Standard five field expressions, or six and seven fields with seconds and years
+    Cron { expression: String },
+    Interval { seconds: u64 },
+    AppStart,
+}
+
+// What to do with runs that were due while the app was closed
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRunPolicy {
+    Skip,
+    #[default]
+    RunOnce,
+    RunAll,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(default)]
+    pub(crate) id: String,
+    pub(crate) script_name: String,
+    pub(crate) kind: ScheduleKind,
+    #[serde(default)]
+    pub(crate) arguments: String,
+    #[serde(default)]
+    pub(crate) values: Option<Vec<Option<String>>>,
+    #[serde(default = "default_true")]
+    pub(crate) hidden: bool,
+    #[serde(default = "default_true")]
+    pub(crate) enabled: bool,
+    #[serde(default)]
+    pub(crate) missed_run_policy: MissedRunPolicy,
+    // Skip an occurrence while an earlier run of the script is still going
+    #[serde(default = "default_true")]
+    pub(crate) skip_if_running: bool,
+    // Unix timestamps in milliseconds
+    #[serde(default)]
+    pub(crate) next_run_at: Option<u64>,
+    #[serde(default)]
+    pub(crate) last_run_at: Option<u64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+lazy_static! {
+    static ref SCHEDULES: Mutex<Vec<Schedule>> = Mutex::new(read_schedules());
+}
+
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+
+fn read_schedules() -> Vec<Schedule> {
+    let path = get_scripts_folder_path().join(format!("{0}.json", SCRIPTS_SCHEDULES_DB));
+    if !path.exists() {
+        return vec![];
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            println!("Script schedules could not be parsed: {}", e);
+            vec![]
+        }),
+        Err(e) => {
+            println!("Script schedules could not be read: {}", e);
+            vec![]
+        }
+    }
+}
+
+fn write_schedules(schedules: &[Schedule]) -> io::Result<()> {
+    let path = get_scripts_folder_path().join(format!("{0}.json", SCRIPTS_SCHEDULES_DB));
+    let data = serde_json::to_string_pretty(schedules)?;
+    fs::write(path, data)
+}
+
+const WEEKDAYS: [&str; 8] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+// Standard cron numbers the days of the week 0-7 from Sunday while the cron crate
+// counts 1-7, day names mean the same to both
+fn weekday_names(field: &str) -> String {
+    let name = |value: &str| {
+        value
+            .parse::<usize>()
+            .ok()
+            .and_then(|day| WEEKDAYS.get(day))
+            .map_or_else(|| value.to_string(), |day| day.to_string())
+    };
+
+    field
+        .split(',')
+        .map(|part| {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, Some(step)),
+                None => (part, None),
+            };
+            let range = match range.split_once('-') {
+                Some(("0", "7")) => "Sun-Sat".to_string(),
+                // Ranges up to 7 end on Sunday, which the crate can only reach by wrapping
+                Some((start, "7")) if step.is_none() => format!("{}-Sat,Sun", name(start)),
+                Some((start, "7")) => format!("{}-Sat", name(start)),
+                Some((start, end)) => format!("{}-{}", name(start), name(end)),
+                None => name(range),
+            };
+            match step {
+                Some(step) => format!("{}/{}", range, step),
+                None => range,
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn parse_cron(expression: &str) -> io::Result<cron::Schedule> {
+    let mut fields = expression.split_whitespace().collect::<Vec<&str>>();
+    // The cron crate expects a seconds field first
+    if fields.len() == 5 {
+        fields.insert(0, "0");
+    }
+    let weekdays = fields.get(5).map(|field| weekday_names(field));
+    let mut fields = fields
+        .into_iter()
+        .map(|field| field.to_string())
+        .collect::<Vec<String>>();
+    if let Some(weekdays) = weekdays {
+        fields[5] = weekdays;
+    }
+
+    cron::Schedule::from_str(&fields.join(" ")).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid cron expression: {}", e),
+        )
+    })
+}
+
+fn to_local(millis: u64) -> DateTime<Local> {
+    Local
+        .timestamp_millis_opt(millis as i64)
+        .single()
+        .unwrap_or_else(Local::now)
+}
+
+// The first occurrence after `from`, none for schedules that only run at app start
+fn next_run_after(kind: &ScheduleKind, from: u64) -> Option<u64> {
+    match kind {
+        ScheduleKind::Cron { expression } => parse_cron(expression)
+            .ok()?
+            .after(&to_local(from))
+            .next()
+            .map(|next| next.timestamp_millis() as u64),
+        ScheduleKind::Interval { seconds } => {
+            Some(from.saturating_add((*seconds).max(1).saturating_mul(1000)))
+        }
+        ScheduleKind::AppStart => None,
+    }
+}
+
+// Occurrences that were due between `from` and now, capped at MAX_MISSED_RUNS
+fn count_missed_runs(kind: &ScheduleKind, from: u64, now: u64) -> usize {
+    let mut count = 0;
+    let mut next = Some(from);
+    while let Some(at) = next.filter(|at| *at <= now) {
+        count += 1;
+        if count >= MAX_MISSED_RUNS {
+            break;
+        }
+        next = next_run_after(kind, at);
+    }
+    count
+}
+
+fn validate_schedule(schedule: &Schedule) -> io::Result<()> {
+    if find_script(Some(&schedule.script_name), "").is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Script {} not found", schedule.script_name),
+        ));
+    }
+
+    match &schedule.kind {
+        ScheduleKind::Cron { expression } => parse_cron(expression).map(|_| ()),
+        ScheduleKind::Interval { seconds } if *seconds == 0 => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Interval must be at least one second",
+        )),
+        ScheduleKind::Interval { seconds } if *seconds > MAX_INTERVAL_SECS => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Interval can be at most {} seconds", MAX_INTERVAL_SECS),
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn list_schedules() -> Vec<Schedule> {
+    SCHEDULES.lock().unwrap().clone()
+}
+
+// Adds a new schedule or replaces the one with the same id, the next run is
+// computed from now so edits never fire for the past
+pub fn upsert_schedule(mut schedule: Schedule) -> io::Result<Schedule> {
+    validate_schedule(&schedule)?;
+
+    if schedule.id.is_empty() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        schedule.id = format!("{:x}", nanos);
+    }
+    schedule.next_run_at = next_run_after(&schedule.kind, now_millis());
+
+    let mut schedules = SCHEDULES.lock().unwrap();
+    match schedules
+        .iter_mut()
+        .find(|existing| existing.id == schedule.id)
+    {
+        Some(existing) => {
+            schedule.last_run_at = existing.last_run_at;
+            *existing = schedule.clone();
+        }
+        None => schedules.push(schedule.clone()),
+    }
+    write_schedules(&schedules)?;
+
+    Ok(schedule)
+}
+
+pub fn remove_schedule(id: &str) -> io::Result<bool> {
+    let mut schedules = SCHEDULES.lock().unwrap();
+    let count = schedules.len();
+    schedules.retain(|schedule| schedule.id != id);
+
+    if schedules.len() == count {
+        return Ok(false);
+    }
+    write_schedules(&schedules)?;
+
+    Ok(true)
+}
+
+fn start_scheduled_run(schedule: &Schedule) -> Option<String> {
+    let script = match saved_script(
+        &schedule.script_name,
+        schedule.hidden,
+        schedule.arguments.clone(),
+        schedule.values.clone(),
+    ) {
+        Ok(script) => script,
+        Err(e) => {
+            println!("Schedule Skipped - {} ({})", schedule.id, e);
+            return None;
+        }
+    };
+
+    if schedule.skip_if_running && !find_runs_by_path(&script.path).is_empty() {
+        println!(
+            "Schedule Skipped - {} ({} is still running)",
+            schedule.id, schedule.script_name
+        );
+        return None;
+    }
+
+    match start_script_with_trigger(&script, "schedule") {
+        Ok(run_id) => {
+            emit(
+                "script_schedule_run",
+                serde_json::json!({ "schedule_id": schedule.id, "run_id": run_id }),
+            );
+            Some(run_id)
+        }
+        Err(e) => {
+            println!("Schedule Failed - {} ({})", schedule.id, e);
+            emit(
+                "script_schedule_error",
+                serde_json::json!({ "schedule_id": schedule.id, "error": e.to_string() }),
+            );
+            None
+        }
+    }
+}
+
+// Replays missed runs one after another so they do not pile up at once
+fn run_missed(schedule: Schedule, count: usize) {
+    thread::spawn(move || {
+        for _ in 0..count {
+            let run_id = match start_scheduled_run(&schedule) {
+                Some(run_id) => run_id,
+                None => break,
+            };
+            while is_running(&run_id) {
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+    });
+}
+
+// Handles app start schedules and runs missed while the app was closed
+fn catch_up() {
+    let now = now_millis();
+    let mut schedules = SCHEDULES.lock().unwrap();
+
+    for schedule in schedules.iter_mut().filter(|schedule| schedule.enabled) {
+        if let ScheduleKind::AppStart = schedule.kind {
+            schedule.last_run_at = Some(now);
+            run_missed(schedule.clone(), 1);
+            continue;
+        }
+
+        let missed = match schedule.next_run_at {
+            Some(next_run_at) if next_run_at <= now => {
+                count_missed_runs(&schedule.kind, next_run_at, now)
+            }
+            Some(_) => continue,
+            None => 0,
+        };
+
+        let runs = match schedule.missed_run_policy {
+            MissedRunPolicy::Skip => 0,
+            MissedRunPolicy::RunOnce => missed.min(1),
+            MissedRunPolicy::RunAll => missed,
+        };
+        if runs > 0 {
+            println!("Schedule Catch Up - {} ({} runs)", schedule.id, runs);
+            schedule.last_run_at = Some(now);
+            run_missed(schedule.clone(), runs);
+        }
+
+        schedule.next_run_at = next_run_after(&schedule.kind, now);
+    }
+
+    if let Err(e) = write_schedules(&schedules) {
+        println!("Script schedules could not be saved: {}", e);
+    }
+}
+
+fn tick() {
+    let now = now_millis();
+    let mut due = vec![];
+
+    {
+        let mut schedules = SCHEDULES.lock().unwrap();
+        for schedule in schedules.iter_mut().filter(|schedule| schedule.enabled) {
+            if schedule.next_run_at.map_or(false, |next| next <= now) {
+                schedule.last_run_at = Some(now);
+                schedule.next_run_at = next_run_after(&schedule.kind, now);
+                due.push(schedule.clone());
+            }
+        }
+
+        if !due.is_empty() {
+            if let Err(e) = write_schedules(&schedules) {
+                println!("Script schedules could not be saved: {}", e);
+            }
+        }
+    }
+
+    for schedule in due {
+        start_scheduled_run(&schedule);
+    }
+}
+
+// Starts the scheduler thread once, it keeps running in background mode as well
+pub fn start_scheduler() {
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    thread::spawn(|| {
+        catch_up();
+        loop {
+            thread::sleep(Duration::from_secs(SCHEDULER_TICK_SECS));
+            tick();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike, Weekday};
+
+    // Monday 2024-01-01 00:00 local time
+    fn monday() -> u64 {
+        Local
+            .with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp_millis() as u64
+    }
+
+    fn upcoming(expression: &str, count: usize) -> Vec<DateTime<Local>> {
+        parse_cron(expression)
+            .unwrap()
+            .after(&to_local(monday()))
+            .take(count)
+            .collect()
+    }
+
+    #[test]
+    fn weekday_numbers_follow_standard_cron() {
+        let days = upcoming("0 9 * * 1-5", 10)
+            .iter()
+            .map(|at| at.weekday())
+            .collect::<Vec<Weekday>>();
+
+        assert_eq!(
+            &days[..5],
+            &[
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri
+            ]
+        );
+        assert_eq!(&days[..5], &days[5..]);
+        assert_eq!(upcoming("0 9 * * 1-5", 1)[0].hour(), 9);
+    }
+
+    #[test]
+    fn sunday_is_zero_or_seven() {
+        for expression in ["* * * * 0", "30 8 * * 7", "0 0 * * Sun"] {
+            assert!(upcoming(expression, 3)
+                .iter()
+                .all(|at| at.weekday() == Weekday::Sun));
+        }
+        assert_eq!(weekday_names("5-7"), "Fri-Sat,Sun");
+        assert_eq!(weekday_names("0-7"), "Sun-Sat");
+        assert_eq!(weekday_names("1,3/2,*"), "Mon,Wed/2,*");
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        assert!(parse_cron("not a cron").is_err());
+        assert!(parse_cron("* * * *").is_err());
+    }
+
+    #[test]
+    fn missed_runs_are_counted_up_to_the_cap() {
+        let hour = 60 * 60 * 1000;
+        let interval = ScheduleKind::Interval { seconds: 60 * 60 };
+        let start = monday();
+
+        assert_eq!(count_missed_runs(&interval, start, start + hour / 2), 1);
+        assert_eq!(count_missed_runs(&interval, start, start + 2 * hour), 3);
+        assert_eq!(
+            count_missed_runs(&interval, start, start + 1000 * hour),
+            MAX_MISSED_RUNS
+        );
+        assert_eq!(count_missed_runs(&interval, start + hour, start), 0);
+        assert_eq!(
+            count_missed_runs(&ScheduleKind::AppStart, start, start + hour),
+            1
+        );
+    }
+
+    #[test]
+    fn huge_intervals_saturate_instead_of_wrapping() {
+        let interval = ScheduleKind::Interval { seconds: u64::MAX };
+
+        assert_eq!(next_run_after(&interval, monday()), Some(u64::MAX));
+        assert_eq!(count_missed_runs(&interval, monday(), monday() + 1000), 1);
+    }
+}
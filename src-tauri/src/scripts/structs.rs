@@ -1,9 +1,169 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+// `value` is the argument type: text, number, label (a flag), choice, file, folder or secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArgumentType {
     pub(crate) label: String,
     pub(crate) value: String,
+    // Option name passed before the value, like "--output"
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    // Used when the run leaves the argument empty, may contain placeholders
+    #[serde(default)]
+    pub(crate) default: Option<String>,
+    #[serde(default)]
+    pub(crate) required: bool,
+    // Allowed values of a choice argument
+    #[serde(default)]
+    pub(crate) options: Vec<String>,
+}
+
+// CPU priority, a nice value on Unix and a priority class on Windows
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Idle,
+    BelowNormal,
+    #[default]
+    Normal,
+    AboveNormal,
+    High,
+}
+
+// Optional limits of a run, a run that goes over one is killed and the reason recorded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    // Wall-clock seconds
+    #[serde(default)]
+    pub(crate) max_runtime_secs: Option<u64>,
+    // Megabytes used by the script and every process it started
+    #[serde(default)]
+    pub(crate) max_memory_mb: Option<u64>,
+    #[serde(default)]
+    pub(crate) priority: Priority,
+}
+
+// How a script file is launched, `Auto` picks from the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpreter {
+    #[default]
+    Auto,
+    Direct,
+    Cmd,
+    PowerShell,
+    Bash,
+    Python,
+    Node,
+}
+
+// Per script launch settings, every field defaults so older databases still load
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptOptions {
+    #[serde(default)]
+    pub(crate) env: HashMap<String, String>,
+    // Path to a .env file, relative paths start at the script's folder
+    #[serde(default)]
+    pub(crate) env_file: Option<String>,
+    #[serde(default)]
+    pub(crate) working_dir: Option<String>,
+    #[serde(default)]
+    pub(crate) interpreter: Interpreter,
+    // Run the script when matching files change
+    #[serde(default)]
+    pub(crate) watch: Vec<WatchRule>,
+    #[serde(default)]
+    pub(crate) limits: ResourceLimits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchEvent {
+    Create,
+    Modify,
+    Remove,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchRule {
+    // Glob like "src/**/*.rs", relative paths start at the script's folder
+    pub(crate) path: String,
+    // Empty reacts to every kind of change
+    #[serde(default)]
+    pub(crate) events: Vec<WatchEvent>,
+    // Quiet time after the last change before the script starts
+    #[serde(default = "default_debounce_ms")]
+    pub(crate) debounce_ms: u64,
+    #[serde(default)]
+    pub(crate) arguments: String,
+    #[serde(default = "default_enabled")]
+    pub(crate) enabled: bool,
+}
+
+fn default_debounce_ms() -> u64 {
+    500
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// How a script is organised in the list, every field defaults so older databases still load
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptMeta {
+    #[serde(default)]
+    pub(crate) description: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    // Folders nest with "/", like "deploy/production"
+    #[serde(default)]
+    pub(crate) group: Option<String>,
+    #[serde(default)]
+    pub(crate) favorite: bool,
+    #[serde(default)]
+    pub(crate) sort_order: i64,
+    // Unix timestamp in milliseconds of the last run
+    #[serde(default)]
+    pub(crate) last_used_at: Option<u64>,
+}
+
+// What the file looked like when it was registered, older entries are hashed on their first run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptIntegrity {
+    // SHA-256 of the file at `path` in lowercase hex
+    #[serde(default)]
+    pub(crate) content_hash: Option<String>,
+    // The original file a copy in scripts/files was made from
+    #[serde(default)]
+    pub(crate) source_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptSort {
+    // Favorites first, then by sort order and name
+    #[default]
+    SortOrder,
+    Name,
+    LastUsed,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptQuery {
+    // Matched against the name, description and path
+    #[serde(default)]
+    pub(crate) text: Option<String>,
+    // Scripts need every tag in the list
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    // Includes the scripts in nested folders of the group
+    #[serde(default)]
+    pub(crate) group: Option<String>,
+    #[serde(default)]
+    pub(crate) favorite: Option<bool>,
+    #[serde(default)]
+    pub(crate) sort: ScriptSort,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,13 +172,30 @@ pub struct ScriptSaveWindow {
     pub(crate) script_args: Vec<ArgumentType>,
     pub(crate) path: String,
     pub(crate) save: bool,
+    #[serde(flatten)]
+    pub(crate) options: ScriptOptions,
+    #[serde(flatten)]
+    pub(crate) meta: ScriptMeta,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScriptSave {
     pub(crate) name: String,
     pub(crate) script_args: Vec<ArgumentType>,
     pub(crate) path: String,
+    #[serde(flatten)]
+    pub(crate) options: ScriptOptions,
+    #[serde(flatten)]
+    pub(crate) meta: ScriptMeta,
+    #[serde(flatten)]
+    pub(crate) integrity: ScriptIntegrity,
+}
+
+// Versioned layout of scripts-info.json, older files are migrated when read
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScriptsDb {
+    pub(crate) version: u32,
+    pub(crate) scripts: Vec<ScriptSave>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,11 +204,77 @@ pub struct ScriptSaveLocal {
     pub(crate) script_args: Vec<ArgumentType>,
     pub(crate) path: String,
     pub(crate) local: bool,
+    #[serde(flatten)]
+    pub(crate) options: ScriptOptions,
+    #[serde(flatten)]
+    pub(crate) meta: ScriptMeta,
+    #[serde(flatten)]
+    pub(crate) integrity: ScriptIntegrity,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Script {
     pub(crate) visibility: String,
     pub(crate) arguments: String,
     pub(crate) path: String,
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    // Values of the saved argument definitions by index, replaces `arguments` when set
+    #[serde(default)]
+    pub(crate) values: Option<Vec<Option<String>>>,
+    // Answers for {prompt} placeholders keyed by label, may hold secrets so they are
+    // never written to the history and a rerun asks again
+    #[serde(default, skip_serializing)]
+    pub(crate) prompt_values: HashMap<String, String>,
+    // Appended after the other arguments, pipelines pass the previous step's result here
+    #[serde(default)]
+    pub(crate) extra_args: Vec<String>,
+    // Set once the user confirmed running a file that changed since it was registered,
+    // never written to the history so a rerun asks again
+    #[serde(default, skip_serializing)]
+    pub(crate) allow_changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub(crate) run_id: String,
+    pub(crate) script_name: String,
+    // The request as it was started, used to re-run with identical arguments
+    pub(crate) script: Script,
+    pub(crate) args: Vec<String>,
+    pub(crate) trigger: String,
+    pub(crate) started_at: u64,
+    pub(crate) ended_at: Option<u64>,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) log_path: String,
+    // Why the app ended the run: "stopped", "timeout" or "memory"
+    #[serde(default)]
+    pub(crate) stop_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningScript {
+    pub(crate) run_id: String,
+    pub(crate) script_name: String,
+    pub(crate) pid: u32,
+    pub(crate) path: String,
+    pub(crate) started_at: u64,
+    pub(crate) uptime_secs: u64,
+    pub(crate) cpu_usage: f32,
+    pub(crate) memory_bytes: u64,
+}
+
+// Body of the API start route, everything falls back to the saved script
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScriptStartOverrides {
+    #[serde(default)]
+    pub(crate) arguments: String,
+    #[serde(default)]
+    pub(crate) values: Option<Vec<Option<String>>>,
+    #[serde(default = "default_hidden")]
+    pub(crate) hidden: bool,
+}
+
+fn default_hidden() -> bool {
+    true
 }
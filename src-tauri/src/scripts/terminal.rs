@@ -1,71 +1,197 @@
+use std::collections::HashMap;
 use std::io;
-use std::os::windows::process::CommandExt;
 use std::path::Path;
-use std::process::Command;
-use winapi::um::wincon::GetConsoleWindow;
-use winapi::um::winuser::{ShowWindow, UpdateWindow, SW_HIDE};
 
-use super::structs::Script;
-
-fn execute_hidden_command(command: &str) -> io::Result<bool> {
-    // Create a new command to execute the PowerShell script
-    let mut cmd = Command::new("powershell.exe");
-
-    // Set the arguments for the PowerShell script
-    cmd.arg("-NoProfile").arg("-Command");
-
-    println!("Service Command - {}", command);
-    cmd.arg(&command);
+use super::arguments::build_argv;
+use super::disk::find_script;
+use super::executor::ExecutionRequest;
+use super::history::get_history_entry;
+use super::integrity::verify_before_run;
+use super::process::{find_runs_by_path, start_run, stop_run};
+use super::structs::{Script, ScriptOptions};
+
+// Splits the argument string the window sends into an argv vector, quoted values stay
+// together and backslashes are kept as they are so Windows paths survive
+fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut has_value = false;
+
+    for character in arguments.chars() {
+        match quote {
+            Some(open) if character == open => quote = None,
+            Some(_) => current.push(character),
+            None if character == '"' || character == '\'' => {
+                quote = Some(character);
+                has_value = true;
+            }
+            None if character.is_whitespace() => {
+                if has_value || !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                    has_value = false;
+                }
+            }
+            None => current.push(character),
+        }
+    }
 
-    // Enable the `DETACHED_PROCESS` flag to hide the terminal window
-    cmd.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
+    if has_value || !current.is_empty() {
+        args.push(current);
+    }
 
-    // Execute the command and capture the output
-    let output = cmd.output()?;
+    args
+}
 
-    // Hide the console window
-    unsafe {
-        let window_handle = GetConsoleWindow();
-        ShowWindow(window_handle, SW_HIDE);
-        UpdateWindow(window_handle);
+// Loads the .env file first so variables set on the script itself take precedence
+fn resolve_env(
+    options: &ScriptOptions,
+    script_folder: &Path,
+) -> io::Result<HashMap<String, String>> {
+    let mut env = HashMap::new();
+
+    if let Some(env_file) = options.env_file.as_ref().filter(|file| !file.is_empty()) {
+        let env_path = script_folder.join(env_file);
+        let entries = dotenvy::from_path_iter(&env_path).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to load {}: {}", env_path.display(), e),
+            )
+        })?;
+
+        for entry in entries {
+            let (key, value) = entry.map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Failed to parse {}: {}", env_path.display(), e),
+                )
+            })?;
+            env.insert(key, value);
+        }
     }
 
-    let stdout_str = String::from_utf8(output.stdout).unwrap();
-    println!("Service Output - {}", stdout_str);
+    env.extend(options.env.clone());
 
-    Ok(true)
+    Ok(env)
 }
 
-pub fn start_script(script: &Script) -> io::Result<bool> {
-    // Construct the PowerShell command to start the service
+fn build_request(script: &Script, args: Vec<String>) -> io::Result<ExecutionRequest> {
     let path = Path::new(&script.path);
-    let parent_folder = path
-        .parent()
-        .unwrap_or_else(|| Path::new("No parent directory found"));
+    let parent_folder = path.parent().unwrap_or_else(|| Path::new("."));
+    let options = find_script(script.name.as_deref(), &script.path)
+        .map(|saved| saved.options)
+        .unwrap_or_default();
+
+    let working_dir = match options.working_dir.as_ref().filter(|dir| !dir.is_empty()) {
+        Some(dir) => parent_folder.join(dir),
+        None => parent_folder.to_path_buf(),
+    };
+
+    Ok(ExecutionRequest {
+        path: path.to_path_buf(),
+        args,
+        working_dir,
+        env: resolve_env(&options, parent_folder)?,
+        hidden: script.visibility == "Hidden",
+        interpreter: options.interpreter,
+        limits: options.limits,
+    })
+}
+
+// Typed values are checked against the saved argument definitions, the plain
+// argument string of older windows is still split into separate arguments
+fn resolve_arguments(script: &Script) -> io::Result<Vec<String>> {
+    let mut args = match &script.values {
+        Some(values) => {
+            let definitions = find_script(script.name.as_deref(), &script.path)
+                .map(|saved| saved.script_args)
+                .unwrap_or_default();
+
+            build_argv(&definitions, values, &script.prompt_values)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        }
+        None => split_arguments(&script.arguments),
+    };
+    args.extend(script.extra_args.iter().cloned());
+
+    Ok(args)
+}
 
-    let mut start_script_command = format!(
-        "Start-Process -FilePath '{0}' -WindowStyle {1} -WorkingDirectory \"{2}\"",
+// Builds the request for a saved script, for runs that no window asked for
+pub fn saved_script(
+    name: &str,
+    hidden: bool,
+    arguments: String,
+    values: Option<Vec<Option<String>>>,
+) -> io::Result<Script> {
+    let saved = find_script(Some(name), "").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Script {} not found", name),
+        )
+    })?;
+
+    Ok(Script {
+        visibility: if hidden { "Hidden" } else { "Normal" }.to_string(),
+        arguments,
+        path: saved.path,
+        name: Some(saved.name),
+        values,
+        prompt_values: HashMap::new(),
+        extra_args: vec![],
+        allow_changed: false,
+    })
+}
+
+// Starts the script and returns the id of the run, `trigger` is recorded in the history
+pub fn start_script_with_trigger(script: &Script, trigger: &str) -> io::Result<String> {
+    verify_before_run(
+        script.name.as_deref(),
         &script.path,
-        &script.visibility,
-        parent_folder.display(),
-    );
-
-    if &script.arguments != "" {
-        start_script_command = format!(
-            "{0} -ArgumentList \"{1}\"",
-            &start_script_command, &script.arguments,
-        );
-    }
+        script.allow_changed,
+        trigger,
+    )?;
+    let request = build_request(script, resolve_arguments(script)?)?;
+
+    start_run(&request, script, trigger)
+}
 
-    let _ = execute_hidden_command(&start_script_command);
+pub fn start_script(script: &Script) -> io::Result<String> {
+    start_script_with_trigger(script, "manual")
+}
 
-    Ok(true)
+// Starts the script again with the exact arguments recorded for an earlier run,
+// `allow_changed` confirms running a file that changed since it was registered
+pub fn rerun_script(run_id: &str, allow_changed: bool) -> io::Result<String> {
+    let record = get_history_entry(run_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Run {} not found in history", run_id),
+        )
+    })?;
+
+    verify_before_run(
+        record.script.name.as_deref(),
+        &record.script.path,
+        allow_changed,
+        "rerun",
+    )?;
+    let request = build_request(&record.script, record.args.clone())?;
+
+    start_run(&request, &record.script, "rerun")
 }
 
-pub fn stop_script(name: &str) -> io::Result<bool> {
-    let stop_service_command = format!("taskkill /IM {0} /F", name);
+// Stops a single run, graceful first and forced after a timeout
+pub fn stop_script(run_id: &str) -> io::Result<bool> {
+    stop_run(run_id)
+}
 
-    let _ = execute_hidden_command(&stop_service_command);
+// Stops every run of the script at the path that this app started
+pub fn stop_script_by_path(path: &str) -> io::Result<bool> {
+    let mut stopped = false;
+    for run_id in find_runs_by_path(path) {
+        stopped |= stop_run(&run_id)?;
+    }
 
-    Ok(true)
+    Ok(stopped)
 }
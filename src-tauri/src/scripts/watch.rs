@@ -0,0 +1,297 @@
+use glob::{MatchOptions, Pattern};
+use lazy_static::lazy_static;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::constants::{WATCH_COOLDOWN_MILLIS, WATCH_TICK_MILLIS};
+use super::disk::{get_script_logs_folder_path, list_scripts};
+use super::process::{find_runs_by_path, is_running};
+use super::structs::{WatchEvent, WatchRule};
+use super::terminal::{saved_script, start_script_with_trigger};
+
+struct ActiveRule {
+    script_name: String,
+    pattern: Pattern,
+    rule: WatchRule,
+}
+
+// Why a rule ignores changes for now, so a script that writes into the folder it
+// watches does not start itself again and again
+enum Quiet {
+    // The run the rule started, until the dispatcher notices it ended
+    Running(String),
+    Until(Instant),
+}
+
+impl Quiet {
+    fn is_active(&self) -> bool {
+        match self {
+            Quiet::Running(_) => true,
+            Quiet::Until(until) => Instant::now() < *until,
+        }
+    }
+}
+
+lazy_static! {
+    static ref WATCHER: Mutex<Option<RecommendedWatcher>> = Mutex::new(None);
+    static ref RULES: Mutex<Vec<ActiveRule>> = Mutex::new(vec![]);
+    // Rule index to the time of its last matching change
+    static ref PENDING: Mutex<HashMap<usize, Instant>> = Mutex::new(HashMap::new());
+    static ref QUIET: Mutex<HashMap<usize, Quiet>> = Mutex::new(HashMap::new());
+}
+
+// `*` and `?` stay inside one folder, only `**` crosses into subfolders
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+static DISPATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+fn event_matches(kind: &EventKind, events: &[WatchEvent]) -> bool {
+    let event = match kind {
+        EventKind::Create(_) => WatchEvent::Create,
+        EventKind::Modify(_) => WatchEvent::Modify,
+        EventKind::Remove(_) => WatchEvent::Remove,
+        _ => return false,
+    };
+
+    events.is_empty() || events.contains(&event)
+}
+
+// The part of the glob before the first wildcard, which is the folder to watch
+fn watch_root(pattern: &Path) -> PathBuf {
+    let mut root = PathBuf::new();
+    for component in pattern.components() {
+        let part = component.as_os_str().to_string_lossy();
+        if part.contains(['*', '?', '[', '{']) {
+            break;
+        }
+        root.push(component);
+    }
+
+    if root.is_dir() {
+        root
+    } else {
+        root.parent()
+            .map(|parent| parent.to_path_buf())
+            .unwrap_or(root)
+    }
+}
+
+fn handle_event(event: notify::Result<notify::Event>) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            println!("Watch Failed - {}", e);
+            return;
+        }
+    };
+
+    // Our own run logs would otherwise trigger a script that watches its folder forever
+    let logs_folder = get_script_logs_folder_path();
+    let rules = RULES.lock().unwrap();
+    let mut pending = PENDING.lock().unwrap();
+    let quiet = QUIET.lock().unwrap();
+
+    for path in event
+        .paths
+        .iter()
+        .filter(|path| !path.starts_with(&logs_folder))
+    {
+        for (index, active) in rules.iter().enumerate() {
+            if quiet.get(&index).map_or(false, Quiet::is_active) {
+                continue;
+            }
+            if event_matches(&event.kind, &active.rule.events)
+                && active.pattern.matches_path_with(path, MATCH_OPTIONS)
+            {
+                pending.insert(index, Instant::now());
+            }
+        }
+    }
+}
+
+// A rule's own run has ended, changes it made while closing its files are still ignored
+// for WATCH_COOLDOWN_MILLIS
+fn update_quiet() {
+    let mut quiet = QUIET.lock().unwrap();
+    for state in quiet.values_mut() {
+        if matches!(state, Quiet::Running(run_id) if !is_running(run_id)) {
+            *state = Quiet::Until(Instant::now() + Duration::from_millis(WATCH_COOLDOWN_MILLIS));
+        }
+    }
+    quiet.retain(|_, state| state.is_active());
+}
+
+// Starts the script of every rule that has been quiet for its debounce time,
+// a rule whose script is still running waits and starts once after it ends
+fn dispatch_pending() {
+    update_quiet();
+
+    // Starting a run hashes the file and may write the database, so the locks the
+    // watcher callback needs are released first
+    let due = {
+        let rules = RULES.lock().unwrap();
+        let mut pending = PENDING.lock().unwrap();
+
+        let due = pending
+            .iter()
+            .filter_map(|(index, changed_at)| {
+                let active = rules.get(*index)?;
+                (changed_at.elapsed() >= Duration::from_millis(active.rule.debounce_ms)).then(
+                    || {
+                        (
+                            *index,
+                            *changed_at,
+                            active.script_name.clone(),
+                            active.rule.arguments.clone(),
+                        )
+                    },
+                )
+            })
+            .collect::<Vec<(usize, Instant, String, String)>>();
+
+        // Rules that went away with a reload are dropped, due ones are taken out
+        pending.retain(|index, _| {
+            rules.get(*index).is_some() && !due.iter().any(|(due_index, ..)| due_index == index)
+        });
+        due
+    };
+
+    for (index, changed_at, script_name, arguments) in due {
+        let script = match saved_script(&script_name, true, arguments, None) {
+            Ok(script) => script,
+            Err(e) => {
+                println!("Watch Skipped - {} ({})", script_name, e);
+                continue;
+            }
+        };
+
+        if !find_runs_by_path(&script.path).is_empty() {
+            // Put back unless the rules were reloaded in the meantime
+            let rules = RULES.lock().unwrap();
+            if rules
+                .get(index)
+                .map_or(false, |active| active.script_name == script_name)
+            {
+                PENDING.lock().unwrap().entry(index).or_insert(changed_at);
+            }
+            continue;
+        }
+
+        match start_script_with_trigger(&script, "watch") {
+            Ok(run_id) => {
+                let rules = RULES.lock().unwrap();
+                if rules
+                    .get(index)
+                    .map_or(false, |active| active.script_name == script_name)
+                {
+                    QUIET.lock().unwrap().insert(index, Quiet::Running(run_id));
+                }
+            }
+            Err(e) => println!("Watch Start Failed - {} ({})", script_name, e),
+        }
+    }
+}
+
+// Rebuilds the watcher from the rules in the scripts database, call after it changed
+pub fn reload_watchers() {
+    let mut rules = vec![];
+    let mut roots = vec![];
+
+    for script in list_scripts() {
+        let script_folder = Path::new(&script.path)
+            .parent()
+            .map(|parent| parent.to_path_buf())
+            .unwrap_or_default();
+
+        for rule in script.options.watch.into_iter().filter(|rule| rule.enabled) {
+            let full_path = if Path::new(&rule.path).is_absolute() {
+                PathBuf::from(&rule.path)
+            } else {
+                script_folder.join(&rule.path)
+            };
+            let full_path = full_path
+                .components()
+                .filter(|component| *component != Component::CurDir)
+                .collect::<PathBuf>();
+
+            let pattern = match Pattern::new(&full_path.to_string_lossy()) {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    println!("Watch Rule Invalid - {} {} ({})", script.name, rule.path, e);
+                    continue;
+                }
+            };
+
+            let root = watch_root(&full_path);
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+            rules.push(ActiveRule {
+                script_name: script.name.clone(),
+                pattern,
+                rule,
+            });
+        }
+    }
+
+    // Dropping the old watcher stops it
+    let mut watcher = WATCHER.lock().unwrap();
+    *watcher = None;
+    PENDING.lock().unwrap().clear();
+    QUIET.lock().unwrap().clear();
+    *RULES.lock().unwrap() = rules;
+
+    if roots.is_empty() {
+        return;
+    }
+
+    let mut new_watcher = match notify::recommended_watcher(handle_event) {
+        Ok(new_watcher) => new_watcher,
+        Err(e) => {
+            println!("Watch Failed - {}", e);
+            return;
+        }
+    };
+    for root in &roots {
+        match new_watcher.watch(root, RecursiveMode::Recursive) {
+            Ok(_) => println!("Watch Started - {}", root.display()),
+            Err(e) => println!("Watch Failed - {} ({})", root.display(), e),
+        }
+    }
+    *watcher = Some(new_watcher);
+
+    if !DISPATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        thread::spawn(|| loop {
+            thread::sleep(Duration::from_millis(WATCH_TICK_MILLIS));
+            dispatch_pending();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_stars_stay_inside_one_folder() {
+        let matches = |pattern: &str, path: &str| {
+            Pattern::new(pattern)
+                .unwrap()
+                .matches_path_with(Path::new(path), MATCH_OPTIONS)
+        };
+
+        assert!(matches("src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "src/a/b/c.rs"));
+        assert!(matches("src/**/*.rs", "src/a/b/c.rs"));
+        assert!(!matches("src/?/c.rs", "src/a/b/c.rs"));
+    }
+}
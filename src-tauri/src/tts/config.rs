@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::PathBuf;
@@ -18,6 +19,35 @@ pub struct TtsConfig {
     pub volume: f32,
     pub api_port: u16,
     pub api_enabled: bool,
+    #[serde(default = "default_voices_cache_ttl_hours")]
+    pub voices_cache_ttl_hours: u64,
+    // Language code to voice, used when the voice is set to "auto"
+    #[serde(default = "default_preferred_voices")]
+    pub preferred_voices: HashMap<String, String>,
+    #[serde(default = "default_auto_voice_fallback")]
+    pub auto_voice_fallback: String,
+    // Bearer token for the script routes of the API server, generated on first start
+    #[serde(default)]
+    pub api_token: String,
+}
+
+fn default_voices_cache_ttl_hours() -> u64 {
+    24
+}
+
+fn default_preferred_voices() -> HashMap<String, String> {
+    HashMap::from([
+        (
+            "en".to_string(),
+            "en-US-AndrewMultilingualNeural".to_string(),
+        ),
+        ("de".to_string(), "de-DE-ConradNeural".to_string()),
+        ("hr".to_string(), "hr-HR-SreckoNeural".to_string()),
+    ])
+}
+
+fn default_auto_voice_fallback() -> String {
+    "en-US-AndrewMultilingualNeural".to_string()
 }
 
 impl Default for TtsConfig {
@@ -32,6 +62,10 @@ impl Default for TtsConfig {
             volume: 1.0,
             api_port: 7891,
             api_enabled: false,
+            voices_cache_ttl_hours: default_voices_cache_ttl_hours(),
+            preferred_voices: default_preferred_voices(),
+            auto_voice_fallback: default_auto_voice_fallback(),
+            api_token: String::new(),
         }
     }
 }
@@ -175,3 +209,66 @@ pub fn update_api_enabled(enabled: bool) -> Result<(), Box<dyn std::error::Error
     config.api_enabled = enabled;
     save_config(&config)
 }
+
+// Returns the API token, creating one when the config has none yet
+pub fn ensure_api_token() -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut config = load_config();
+    if config.api_token.is_empty() {
+        config.api_token = crate::utils::secrets::generate_token();
+        save_config(&config)?;
+    }
+    Ok(config.api_token)
+}
+
+pub fn regenerate_api_token() -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut config = load_config();
+    config.api_token = crate::utils::secrets::generate_token();
+    save_config(&config)?;
+    Ok(config.api_token)
+}
+
+pub fn update_voices_cache_ttl_hours(
+    hours: u64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut config = load_config();
+    config.voices_cache_ttl_hours = hours;
+    save_config(&config)
+}
+
+pub fn update_preferred_voice(
+    language: &str,
+    voice: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut config = load_config();
+    match voice {
+        Some(voice) => {
+            config
+                .preferred_voices
+                .insert(language.to_lowercase(), voice.to_string());
+        }
+        None => {
+            config.preferred_voices.remove(&language.to_lowercase());
+        }
+    }
+
+    // Auto voice selection may now pick a different voice for the play mode text
+    if config.play_mode {
+        let _ = crate::tts::tts::delete_play_mode_audio();
+    }
+
+    save_config(&config)
+}
+
+pub fn update_auto_voice_fallback(
+    voice: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut config = load_config();
+    config.auto_voice_fallback = voice.to_string();
+
+    // Auto voice selection may now pick a different voice for the play mode text
+    if config.play_mode {
+        let _ = crate::tts::tts::delete_play_mode_audio();
+    }
+
+    save_config(&config)
+}
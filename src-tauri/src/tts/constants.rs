@@ -1,4 +1,7 @@
 pub const EXPORT_FOLDER_NAME: &str = "export";
 pub const CONFIG_FOLDER_NAME: &str = "config";
-pub const VOICES_FILE_NAME: &str = "voices.txt";
+pub const VOICES_FILE_NAME: &str = "voices.json";
+pub const LEXICON_FILE_NAME: &str = "lexicon.json";
 pub const PLAY_MODE_AUDIO_FILE: &str = "play";
+pub const PREVIEWS_FOLDER_NAME: &str = "previews";
+pub const AUTO_VOICE: &str = "auto";
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use whatlang::detect;
+
+use crate::tts::config::TtsConfig;
+
+// ISO 639-3 codes reported by the detector mapped to the ISO 639-1 codes used in voice locales
+const LANGUAGE_CODES: &[(&str, &str)] = &[
+    ("eng", "en"),
+    ("deu", "de"),
+    ("hrv", "hr"),
+    ("srp", "sr"),
+    ("bos", "bs"),
+    ("slv", "sl"),
+    ("fra", "fr"),
+    ("spa", "es"),
+    ("ita", "it"),
+    ("por", "pt"),
+    ("nld", "nl"),
+    ("pol", "pl"),
+    ("ces", "cs"),
+    ("slk", "sk"),
+    ("hun", "hu"),
+    ("rus", "ru"),
+    ("ukr", "uk"),
+    ("swe", "sv"),
+    ("dan", "da"),
+    ("nob", "nb"),
+    ("fin", "fi"),
+    ("tur", "tr"),
+    ("jpn", "ja"),
+    ("cmn", "zh"),
+    ("kor", "ko"),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LanguageSegment {
+    pub language: Option<String>,
+    pub voice: String,
+    pub text: String,
+}
+
+pub fn detect_language(text: &str) -> Option<String> {
+    let info = detect(text)?;
+    // Short or ambiguous text is better read by the fallback voice than a wrong guess
+    if !info.is_reliable() && info.confidence() < 0.5 {
+        return None;
+    }
+
+    let code = info.lang().code();
+    LANGUAGE_CODES
+        .iter()
+        .find(|(long, _)| *long == code)
+        .map(|(_, short)| short.to_string())
+}
+
+pub fn select_voice(language: Option<&str>, config: &TtsConfig) -> String {
+    language
+        .and_then(|language| config.preferred_voices.get(language))
+        .cloned()
+        .unwrap_or_else(|| config.auto_voice_fallback.clone())
+}
+
+// Paragraphs are separated by blank lines, with Unix or Windows line endings
+fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = vec![];
+    let mut current: Vec<&str> = vec![];
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join("\n"));
+                current.clear();
+            }
+            continue;
+        }
+        current.push(line);
+    }
+    if !current.is_empty() {
+        paragraphs.push(current.join("\n"));
+    }
+
+    paragraphs
+}
+
+// Splits the text into paragraphs, picks a voice for each one and merges
+// neighbouring paragraphs that ended up with the same voice
+pub fn split_by_language(text: &str, config: &TtsConfig) -> Vec<LanguageSegment> {
+    let mut segments: Vec<LanguageSegment> = vec![];
+
+    for paragraph in paragraphs(text) {
+        let paragraph = paragraph.trim();
+
+        let language = detect_language(paragraph);
+        let voice = select_voice(language.as_deref(), config);
+
+        match segments.last_mut() {
+            Some(last) if last.voice == voice => {
+                last.text.push_str("\n\n");
+                last.text.push_str(paragraph);
+            }
+            _ => segments.push(LanguageSegment {
+                language,
+                voice,
+                text: paragraph.to_string(),
+            }),
+        }
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &str =
+        "The weather is lovely today and we are going for a long walk in the park.";
+    const GERMAN: &str =
+        "Das Wetter ist heute wunderschön und wir machen einen langen Spaziergang im Park.";
+
+    #[test]
+    fn paragraphs_split_on_blank_lines_with_any_line_ending() {
+        assert_eq!(
+            paragraphs("one\r\ntwo\r\n\r\nthree"),
+            vec!["one\ntwo", "three"]
+        );
+        assert_eq!(paragraphs("one\n  \n\nthree\n"), vec!["one", "three"]);
+        assert!(paragraphs("\r\n\r\n").is_empty());
+    }
+
+    #[test]
+    fn split_by_language_picks_a_voice_per_paragraph() {
+        let config = TtsConfig::default();
+        let text = format!("{}\r\n\r\n{}", ENGLISH, GERMAN);
+
+        let segments = split_by_language(&text, &config);
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].language.as_deref(), Some("en"));
+        assert_eq!(segments[0].voice, config.preferred_voices["en"]);
+        assert_eq!(segments[1].language.as_deref(), Some("de"));
+        assert_eq!(segments[1].voice, config.preferred_voices["de"]);
+    }
+
+    #[test]
+    fn split_by_language_merges_paragraphs_with_the_same_voice() {
+        let config = TtsConfig::default();
+        let text = format!("{}\n\n{}", ENGLISH, ENGLISH);
+
+        let segments = split_by_language(&text, &config);
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, format!("{}\n\n{}", ENGLISH, ENGLISH));
+    }
+}
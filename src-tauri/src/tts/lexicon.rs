@@ -0,0 +1,348 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::tts::config::load_config;
+use crate::tts::constants::{AUTO_VOICE, CONFIG_FOLDER_NAME, LEXICON_FILE_NAME};
+use crate::tts::language::split_by_language;
+use crate::tts::voices::find_voice;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LexiconKind {
+    // Plain text written in place of the match
+    Replacement,
+    // IPA pronunciation wrapped around the match as SSML
+    Phoneme,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LexiconEntry {
+    #[serde(default)]
+    pub id: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    pub replacement: String,
+    #[serde(default = "default_kind")]
+    pub kind: LexiconKind,
+    // Limit the entry to a voice short name or a locale like "en-US" or "en"
+    #[serde(default)]
+    pub voice: Option<String>,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_kind() -> LexiconKind {
+    LexiconKind::Replacement
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NormalizedText {
+    pub original: String,
+    pub normalized: String,
+    pub applied: Vec<String>,
+}
+
+fn get_lexicon_path() -> PathBuf {
+    let mut path = PathBuf::from(CONFIG_FOLDER_NAME);
+    path.push(LEXICON_FILE_NAME);
+    path
+}
+
+pub fn load_lexicon() -> Vec<LexiconEntry> {
+    let path = get_lexicon_path();
+    if !path.exists() {
+        return vec![];
+    }
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open lexicon file: {}", e);
+            return vec![];
+        }
+    };
+
+    match serde_json::from_reader(file) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Failed to parse lexicon file: {}", e);
+            vec![]
+        }
+    }
+}
+
+fn save_lexicon(entries: &[LexiconEntry]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = get_lexicon_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(entries)?;
+    fs::write(path, json)?;
+
+    Ok(())
+}
+
+fn build_regex(entry: &LexiconEntry) -> Result<Regex, regex::Error> {
+    let pattern = if entry.is_regex {
+        entry.pattern.clone()
+    } else {
+        regex::escape(&entry.pattern)
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!entry.case_sensitive)
+        .build()
+}
+
+// Adds a new entry or replaces the one with the same id, returning the stored entry
+pub fn upsert_lexicon_entry(
+    mut entry: LexiconEntry,
+) -> Result<LexiconEntry, Box<dyn std::error::Error + Send + Sync>> {
+    if entry.pattern.is_empty() {
+        return Err("Lexicon pattern is empty".into());
+    }
+    build_regex(&entry).map_err(|e| format!("Invalid lexicon pattern: {}", e))?;
+
+    let mut entries = load_lexicon();
+
+    if entry.id.is_empty() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        entry.id = format!("{:x}", nanos);
+    }
+
+    match entries.iter_mut().find(|existing| existing.id == entry.id) {
+        Some(existing) => *existing = entry.clone(),
+        None => entries.push(entry.clone()),
+    }
+
+    save_lexicon(&entries)?;
+
+    // The play mode audio may have been generated with the old pronunciation
+    let _ = crate::tts::tts::delete_play_mode_audio();
+
+    Ok(entry)
+}
+
+pub fn remove_lexicon_entry(id: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let mut entries = load_lexicon();
+    let count = entries.len();
+    entries.retain(|entry| entry.id != id);
+
+    if entries.len() == count {
+        return Ok(false);
+    }
+
+    save_lexicon(&entries)?;
+
+    // The play mode audio may have been generated with the old pronunciation
+    let _ = crate::tts::tts::delete_play_mode_audio();
+
+    Ok(true)
+}
+
+fn entry_applies(entry: &LexiconEntry, voice: &str, locale: &str) -> bool {
+    if !entry.enabled {
+        return false;
+    }
+
+    let voice_matches = entry
+        .voice
+        .as_ref()
+        .map_or(true, |scope| scope.eq_ignore_ascii_case(voice));
+
+    // A scope of "en" covers every "en-XX" locale
+    let locale_matches = entry.locale.as_ref().map_or(true, |scope| {
+        locale.eq_ignore_ascii_case(scope)
+            || locale
+                .to_lowercase()
+                .starts_with(&format!("{}-", scope.to_lowercase()))
+    });
+
+    voice_matches && locale_matches
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// Runs the lexicon entries scoped to the voice over the text, in file order
+pub fn normalize_text(text: &str, voice: &str, locale: &str) -> NormalizedText {
+    apply_entries(text, &load_lexicon(), voice, locale)
+}
+
+fn apply_entries(
+    text: &str,
+    entries: &[LexiconEntry],
+    voice: &str,
+    locale: &str,
+) -> NormalizedText {
+    let mut normalized = text.to_string();
+    let mut applied = vec![];
+
+    for entry in entries
+        .iter()
+        .filter(|entry| entry_applies(entry, voice, locale))
+    {
+        let regex = match build_regex(entry) {
+            Ok(regex) => regex,
+            Err(e) => {
+                eprintln!("Skipping invalid lexicon entry {}: {}", entry.id, e);
+                continue;
+            }
+        };
+
+        if !regex.is_match(&normalized) {
+            continue;
+        }
+
+        normalized = match entry.kind {
+            // Only regex entries may use $1 or $name to refer to their groups
+            LexiconKind::Replacement if entry.is_regex => regex
+                .replace_all(&normalized, entry.replacement.as_str())
+                .into_owned(),
+            LexiconKind::Replacement => regex
+                .replace_all(&normalized, regex::NoExpand(&entry.replacement))
+                .into_owned(),
+            LexiconKind::Phoneme => regex
+                .replace_all(&normalized, |captures: &regex::Captures| {
+                    format!(
+                        "<phoneme alphabet=\"ipa\" ph=\"{}\">{}</phoneme>",
+                        escape_xml(&entry.replacement),
+                        &captures[0]
+                    )
+                })
+                .into_owned(),
+        };
+        applied.push(entry.id.clone());
+    }
+
+    NormalizedText {
+        original: text.to_string(),
+        normalized,
+        applied,
+    }
+}
+
+// Debug view of what the synthesizer receives for the text and voice,
+// "auto" normalizes each language segment with the voice picked for it
+pub fn normalize_for_voice(text: &str, voice: &str) -> NormalizedText {
+    let segments = if voice == AUTO_VOICE {
+        split_by_language(text, &load_config())
+            .into_iter()
+            .map(|segment| (segment.voice, segment.text))
+            .collect::<Vec<(String, String)>>()
+    } else {
+        vec![(voice.to_string(), text.to_string())]
+    };
+
+    let mut normalized = vec![];
+    let mut applied = vec![];
+    for (voice, segment_text) in segments {
+        let locale = find_voice(&voice)
+            .map(|voice| voice.locale)
+            .unwrap_or_default();
+        let result = normalize_text(&segment_text, &voice, &locale);
+        normalized.push(result.normalized);
+        for id in result.applied {
+            if !applied.contains(&id) {
+                applied.push(id);
+            }
+        }
+    }
+
+    NormalizedText {
+        original: text.to_string(),
+        normalized: normalized.join("\n\n"),
+        applied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pattern: &str, replacement: &str, is_regex: bool, kind: LexiconKind) -> LexiconEntry {
+        LexiconEntry {
+            id: pattern.to_string(),
+            pattern: pattern.to_string(),
+            is_regex,
+            case_sensitive: false,
+            replacement: replacement.to_string(),
+            kind,
+            voice: None,
+            locale: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn literal_replacements_keep_dollar_signs() {
+        let entries = vec![entry("USD", "US$ $1", false, LexiconKind::Replacement)];
+
+        let result = apply_entries("Costs 5 usd", &entries, "en-US-AndrewNeural", "en-US");
+
+        assert_eq!(result.normalized, "Costs 5 US$ $1");
+        assert_eq!(result.applied, vec!["USD"]);
+    }
+
+    #[test]
+    fn regex_replacements_expand_groups() {
+        let entries = vec![entry(
+            r"(\d+)km",
+            "$1 kilometers",
+            true,
+            LexiconKind::Replacement,
+        )];
+
+        let result = apply_entries("Run 5km", &entries, "en-US-AndrewNeural", "en-US");
+
+        assert_eq!(result.normalized, "Run 5 kilometers");
+    }
+
+    #[test]
+    fn phonemes_escape_the_pronunciation() {
+        let entries = vec![entry("tomato", "tə\"<&", false, LexiconKind::Phoneme)];
+
+        let result = apply_entries("A tomato", &entries, "en-US-AndrewNeural", "en-US");
+
+        assert_eq!(
+            result.normalized,
+            "A <phoneme alphabet=\"ipa\" ph=\"tə&quot;&lt;&amp;\">tomato</phoneme>"
+        );
+    }
+
+    #[test]
+    fn entries_only_apply_to_their_scope() {
+        let mut scoped = entry("hello", "servus", false, LexiconKind::Replacement);
+        scoped.locale = Some("de".to_string());
+        let mut disabled = entry("world", "welt", false, LexiconKind::Replacement);
+        disabled.enabled = false;
+        let entries = vec![scoped, disabled];
+
+        let english = apply_entries("hello world", &entries, "en-US-AndrewNeural", "en-US");
+        let german = apply_entries("hello world", &entries, "de-DE-ConradNeural", "de-DE");
+
+        assert_eq!(english.normalized, "hello world");
+        assert_eq!(german.normalized, "servus world");
+    }
+}
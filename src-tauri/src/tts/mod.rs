@@ -1,4 +1,8 @@
 pub mod config;
 pub mod constants;
 pub mod disk;
+pub mod language;
+pub mod lexicon;
+pub mod preview;
 pub mod tts;
+pub mod voices;
@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use crate::tts::config::load_config;
+use crate::tts::constants::PREVIEWS_FOLDER_NAME;
+use crate::tts::tts::{check_audio_file_exists, generate_tts_synthesis, get_audio_file_path};
+use crate::tts::voices::find_voice;
+
+const DEFAULT_SAMPLE: &str = "Hello! This is how I sound when reading your text.";
+
+// Short sample sentences keyed by language code
+const SAMPLE_SENTENCES: &[(&str, &str)] = &[
+    ("en", DEFAULT_SAMPLE),
+    ("de", "Hallo! So klinge ich, wenn ich deinen Text vorlese."),
+    ("hr", "Bok! Ovako zvučim kada čitam tvoj tekst."),
+    ("sr", "Zdravo! Ovako zvučim kada čitam tvoj tekst."),
+    ("bs", "Zdravo! Ovako zvučim kada čitam tvoj tekst."),
+    ("sl", "Živjo! Tako zvenim, ko berem tvoje besedilo."),
+    ("fr", "Bonjour ! Voici ma voix quand je lis votre texte."),
+    ("es", "¡Hola! Así sueno cuando leo tu texto."),
+    ("it", "Ciao! Ecco come suono quando leggo il tuo testo."),
+    ("pt", "Olá! É assim que eu soo ao ler o seu texto."),
+    ("nl", "Hallo! Zo klink ik wanneer ik je tekst voorlees."),
+    ("pl", "Cześć! Tak brzmię, gdy czytam twój tekst."),
+    ("cs", "Ahoj! Takhle zním, když čtu tvůj text."),
+    ("sk", "Ahoj! Takto znie môj hlas, keď čítam tvoj text."),
+    ("hu", "Szia! Így hangzom, amikor felolvasom a szövegedet."),
+    ("ru", "Привет! Вот так я звучу, когда читаю ваш текст."),
+    ("uk", "Привіт! Ось так я звучу, коли читаю ваш текст."),
+    ("sv", "Hej! Så här låter jag när jag läser din text."),
+    ("da", "Hej! Sådan lyder jeg, når jeg læser din tekst."),
+    ("nb", "Hei! Slik høres jeg ut når jeg leser teksten din."),
+    ("fi", "Hei! Tältä kuulostan, kun luen tekstisi."),
+    ("tr", "Merhaba! Metninizi okurken sesim böyle çıkıyor."),
+    ("ja", "こんにちは。これが私の読み上げの声です。"),
+    ("zh", "你好！这是我朗读文字时的声音。"),
+    ("ko", "안녕하세요! 제가 글을 읽을 때 이런 목소리예요."),
+];
+
+pub fn get_sample_sentence(locale: &str) -> &'static str {
+    let language = locale.split('-').next().unwrap_or_default();
+    SAMPLE_SENTENCES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(language))
+        .map(|(_, sentence)| *sentence)
+        .unwrap_or(DEFAULT_SAMPLE)
+}
+
+// The cache key includes the voice settings so a change regenerates the sample
+fn get_preview_name(voice: &str, pitch: f32, rate: f32, volume: f32) -> String {
+    format!(
+        "{}/{}_p{:.2}_r{:.2}_v{:.2}",
+        PREVIEWS_FOLDER_NAME, voice, pitch, rate, volume
+    )
+}
+
+// Synthesizes the sample sentence for the voice using the current pitch, rate
+// and volume, reusing the cached file when it already exists
+pub fn preview_voice(voice: &str) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let voice_info = find_voice(voice).ok_or_else(|| format!("Voice not found: {}", voice))?;
+    let config = load_config();
+    let name = get_preview_name(voice, config.pitch, config.rate, config.volume);
+
+    if check_audio_file_exists(&name) {
+        println!("🎧 Using cached preview for voice '{}'", voice);
+        return Ok(get_audio_file_path(&name));
+    }
+
+    generate_tts_synthesis(
+        get_sample_sentence(&voice_info.locale),
+        &name,
+        voice,
+        config.pitch,
+        config.rate,
+        config.volume,
+    )?;
+
+    Ok(get_audio_file_path(&name))
+}
@@ -1,6 +1,6 @@
 use std::fs;
 use std::fs::File;
-use std::io::{BufRead, Write};
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::thread;
@@ -8,15 +8,16 @@ use std::thread;
 use rodio::{Decoder, OutputStream, Sink};
 use std::io::BufReader;
 
-use msedge_tts::{
-    tts::{
-        client::{connect, SynthesizedAudio},
-        SpeechConfig,
-    },
-    voice::{get_voices_list, Voice},
+use msedge_tts::tts::{
+    client::{connect, SynthesizedAudio},
+    SpeechConfig,
 };
 
-use crate::tts::constants::{CONFIG_FOLDER_NAME, EXPORT_FOLDER_NAME, VOICES_FILE_NAME};
+use crate::tts::config::load_config;
+use crate::tts::constants::{AUTO_VOICE, EXPORT_FOLDER_NAME};
+use crate::tts::language::split_by_language;
+use crate::tts::lexicon::normalize_text;
+use crate::tts::voices::{find_voice, VoiceInfo};
 
 // Global state for audio playback
 static mut AUDIO_PLAYING: bool = false;
@@ -26,60 +27,18 @@ static mut PLAYBACK_THREAD: Option<std::thread::JoinHandle<()>> = None;
 // Control channel for stopping audio
 static mut STOP_REQUESTED: bool = false;
 
-fn get_voice(name: &str) -> Result<Voice, Box<dyn std::error::Error + Send + Sync>> {
-    let voices = get_voices_list().unwrap();
-    let voice = voices
-        .into_iter()
-        .find(|voice| voice.short_name.as_deref() == Some(name))
-        .ok_or_else(|| "Voice not found".to_string())?;
-    Ok(voice)
-}
-
-pub fn fetch_voices_list() -> Vec<(String, String)> {
-    let voices_list = get_voices_list().unwrap();
-    // map and return short_name and locale
-    let voices = voices_list
-        .into_iter()
-        .map(|voice| (voice.short_name.unwrap(), voice.locale.unwrap()))
-        .collect::<Vec<(String, String)>>();
-
-    voices
-}
-
-pub fn save_voices_list() {
-    let voices = fetch_voices_list();
-    // save to file
-    let mut path = PathBuf::from(CONFIG_FOLDER_NAME);
-    std::fs::create_dir_all(&path).unwrap(); // This line creates the "config" directory if it does not exist
-    path.push(VOICES_FILE_NAME);
-    let mut file = File::create(path).unwrap();
-    for (name, locale) in &voices {
-        file.write_all(format!("{} {}\n", name, locale).as_bytes())
-            .unwrap();
-    }
-}
-
-pub fn get_voices_list_names() -> Vec<(String, String)> {
-    // check if file exists, if not fetch and save
-    let mut path = PathBuf::from(CONFIG_FOLDER_NAME);
-    path.push(VOICES_FILE_NAME);
-    if !path.exists() {
-        return vec![];
+fn get_speech_config(voice: VoiceInfo) -> SpeechConfig {
+    let audio_format = voice
+        .suggested_codec
+        .unwrap_or_else(|| "audio-24khz-48kbitrate-mono-mp3".to_string());
+
+    SpeechConfig {
+        voice_name: voice.name,
+        audio_format,
+        pitch: 0,
+        rate: 0,
+        volume: 0,
     }
-    // now read from file
-    let file = File::open(path).unwrap();
-    let reader = std::io::BufReader::new(file);
-    let voices = reader
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
-            let mut parts = line.split_whitespace();
-            let name = parts.next().unwrap().to_string();
-            let locale = parts.next().unwrap().to_string();
-            (name, locale)
-        })
-        .collect::<Vec<(String, String)>>();
-    return voices;
 }
 
 fn get_audio_stream(
@@ -94,16 +53,83 @@ fn get_audio_stream(
     let rate_scaled = (rate * 100.0) as i32 - 100; // Map 0.0-2.0 to -100 to 100
     let volume_scaled = (volume * 100.0) as i32; // Map 0.0-1.0 to 0 to 100
 
-    let voice = get_voice(name)?;
+    let voice = find_voice(name).ok_or_else(|| "Voice not found".to_string())?;
+
+    // Apply the pronunciation lexicon before the text reaches the synthesizer
+    let normalized = normalize_text(text, &voice.short_name, &voice.locale);
+    if !normalized.applied.is_empty() {
+        println!("🔤 Normalized text: {}", normalized.normalized);
+    }
+
+    let mut config = get_speech_config(voice);
     let mut tts = connect()?;
-    let mut config = SpeechConfig::from(&voice);
     config.pitch = pitch_scaled;
     config.rate = rate_scaled;
     config.volume = volume_scaled;
-    let audio_stream = tts.synthesize(text, &config)?;
+    let audio_stream = tts.synthesize(&normalized.normalized, &config)?;
     Ok(audio_stream)
 }
 
+// Detects the language of each paragraph and reads it with the preferred voice,
+// the MP3 frames of all segments are joined into a single stream
+fn get_auto_voice_audio(
+    text: &str,
+    pitch: f32,
+    rate: f32,
+    volume: f32,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let config = load_config();
+    let segments = split_by_language(text, &config);
+    let mut audio_bytes = vec![];
+
+    for segment in segments {
+        println!(
+            "🌍 Segment language {:?}, using voice '{}'",
+            segment.language, segment.voice
+        );
+        let audio_stream = get_audio_stream(&segment.text, &segment.voice, pitch, rate, volume)?;
+        audio_bytes.extend_from_slice(&audio_stream.audio_bytes);
+    }
+
+    if audio_bytes.is_empty() {
+        return Err("No audio was generated for the text".into());
+    }
+
+    Ok(audio_bytes)
+}
+
+// Synthesizes the text into MP3 bytes without writing a file
+pub fn synthesize_audio(
+    text: &str,
+    voice: &str,
+    pitch: f32,
+    rate: f32,
+    volume: f32,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    if text.is_empty() {
+        return Err("Text is empty".into());
+    }
+
+    let audio_bytes = if voice == AUTO_VOICE {
+        get_auto_voice_audio(text, pitch, rate, volume)?
+    } else {
+        let audio_stream = get_audio_stream(text, voice, pitch, rate, volume)?;
+
+        // Optionally, handle audio metadata if needed
+        for metadata in audio_stream.audio_metadata {
+            println!("ℹ️ Audio metadata: {:?}", metadata);
+        }
+
+        audio_stream.audio_bytes
+    };
+    println!(
+        "✅ Obtained audio stream, size: {} bytes",
+        audio_bytes.len()
+    );
+
+    Ok(audio_bytes)
+}
+
 pub fn generate_tts_synthesis(
     text: &str,
     name: &str,
@@ -117,15 +143,7 @@ pub fn generate_tts_synthesis(
         name, voice
     );
 
-    if text.is_empty() {
-        return Err("Text is empty".into());
-    }
-
-    let audio_stream = get_audio_stream(text, voice, pitch, rate, volume)?;
-    println!(
-        "✅ Obtained audio stream, size: {} bytes",
-        audio_stream.audio_bytes.len()
-    );
+    let audio_bytes = synthesize_audio(text, voice, pitch, rate, volume)?;
 
     // Get the full path to the audio file using our updated function
     let path = get_audio_file_path(name);
@@ -151,7 +169,7 @@ pub fn generate_tts_synthesis(
     let mut file = File::create(&path)
         .map_err(|e| format!("Failed to create file {}: {}", path.display(), e))?;
 
-    file.write_all(&audio_stream.audio_bytes)
+    file.write_all(&audio_bytes)
         .map_err(|e| format!("Failed to write audio data to {}: {}", path.display(), e))?;
 
     // Flush to ensure data is written to disk
@@ -171,11 +189,6 @@ pub fn generate_tts_synthesis(
         path.display()
     );
 
-    // Optionally, handle audio metadata if needed
-    for metadata in audio_stream.audio_metadata {
-        println!("ℹ️ Audio metadata: {:?}", metadata);
-    }
-
     Ok(())
 }
 
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use msedge_tts::voice::{get_voices_list, Voice};
+
+use crate::tts::config::load_config;
+use crate::tts::constants::{CONFIG_FOLDER_NAME, VOICES_FILE_NAME};
+
+// Shipped with the binary so the app has voices before the first successful fetch
+const DEFAULT_VOICES: &str = include_str!("../../resources/default_voices.json");
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoiceInfo {
+    pub name: String,
+    pub short_name: String,
+    pub friendly_name: String,
+    pub gender: String,
+    pub locale: String,
+    pub language: String,
+    pub suggested_codec: Option<String>,
+    pub status: String,
+    // The Edge voice list reports personalities and content categories
+    // instead of Azure styles and roles, so we store them under those names
+    pub styles: Vec<String>,
+    pub roles: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VoiceCatalog {
+    // Unix timestamp in seconds, 0 for the bundled list
+    pub fetched_at: u64,
+    pub voices: Vec<VoiceInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoiceCatalogStatus {
+    pub fetched_at: u64,
+    pub ttl_hours: u64,
+    pub stale: bool,
+    pub bundled: bool,
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VoiceQuery {
+    pub locale: Option<String>,
+    pub language: Option<String>,
+    pub gender: Option<String>,
+    pub text: Option<String>,
+}
+
+impl From<Voice> for VoiceInfo {
+    fn from(voice: Voice) -> Self {
+        let short_name = voice.short_name.unwrap_or_else(|| voice.name.clone());
+        let locale = voice.locale.unwrap_or_default();
+        let language = locale.split('-').next().unwrap_or_default().to_string();
+        let (styles, roles) = match voice.voice_tag {
+            Some(tag) => (
+                tag.voice_personalities.unwrap_or_default(),
+                tag.content_categories.unwrap_or_default(),
+            ),
+            None => (vec![], vec![]),
+        };
+
+        Self {
+            name: voice.name,
+            friendly_name: voice.friendly_name.unwrap_or_else(|| short_name.clone()),
+            short_name,
+            gender: voice.gender.unwrap_or_default(),
+            locale,
+            language,
+            suggested_codec: voice.suggested_codec,
+            status: voice.status.unwrap_or_default(),
+            styles,
+            roles,
+        }
+    }
+}
+
+fn get_voices_file_path() -> PathBuf {
+    let mut path = PathBuf::from(CONFIG_FOLDER_NAME);
+    path.push(VOICES_FILE_NAME);
+    path
+}
+
+pub fn fetch_voices_list() -> Result<Vec<VoiceInfo>, Box<dyn std::error::Error + Send + Sync>> {
+    let voices = get_voices_list()?
+        .into_iter()
+        .map(VoiceInfo::from)
+        .collect::<Vec<VoiceInfo>>();
+
+    Ok(voices)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn get_bundled_catalog() -> VoiceCatalog {
+    serde_json::from_str(DEFAULT_VOICES).unwrap_or_else(|e| {
+        eprintln!("Failed to parse bundled voices list: {}", e);
+        VoiceCatalog::default()
+    })
+}
+
+fn read_cached_catalog() -> Option<VoiceCatalog> {
+    let path = get_voices_file_path();
+    if !path.exists() {
+        return None;
+    }
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open voices file: {}", e);
+            return None;
+        }
+    };
+
+    match serde_json::from_reader::<_, VoiceCatalog>(file) {
+        Ok(catalog) if !catalog.voices.is_empty() => Some(catalog),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("Failed to parse voices file: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save_voices_list() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let voices = fetch_voices_list()?;
+    if voices.is_empty() {
+        return Err("Voice service returned an empty list".into());
+    }
+
+    let catalog = VoiceCatalog {
+        fetched_at: now_secs(),
+        voices,
+    };
+
+    let path = get_voices_file_path();
+    // Ensure config directory exists
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(&catalog)?;
+    fs::write(path, json)?;
+
+    Ok(())
+}
+
+pub fn load_catalog() -> VoiceCatalog {
+    read_cached_catalog().unwrap_or_else(get_bundled_catalog)
+}
+
+pub fn load_voices() -> Vec<VoiceInfo> {
+    load_catalog().voices
+}
+
+pub fn get_catalog_status() -> VoiceCatalogStatus {
+    let catalog = load_catalog();
+    let ttl_hours = load_config().voices_cache_ttl_hours;
+    let age = now_secs().saturating_sub(catalog.fetched_at);
+
+    VoiceCatalogStatus {
+        fetched_at: catalog.fetched_at,
+        ttl_hours,
+        stale: catalog.fetched_at == 0 || age > ttl_hours.saturating_mul(60 * 60),
+        bundled: catalog.fetched_at == 0,
+        count: catalog.voices.len(),
+    }
+}
+
+// Refreshes the cached catalog on a separate thread and reports the outcome,
+// the previous catalog stays in place when the refresh fails
+pub fn refresh_voices_in_background<F>(on_done: F)
+where
+    F: FnOnce(Result<(), String>) + Send + 'static,
+{
+    thread::spawn(move || {
+        println!("🔄 Refreshing voices list");
+        let result = save_voices_list().map_err(|e| e.to_string());
+        match &result {
+            Ok(_) => println!("✅ Voices list refreshed"),
+            Err(e) => println!("❌ Failed to refresh voices list: {}", e),
+        }
+        on_done(result);
+    });
+}
+
+pub fn refresh_voices_if_stale<F>(on_done: F)
+where
+    F: FnOnce(Result<(), String>) + Send + 'static,
+{
+    if get_catalog_status().stale {
+        refresh_voices_in_background(on_done);
+    }
+}
+
+pub fn find_voice(short_name: &str) -> Option<VoiceInfo> {
+    load_voices()
+        .into_iter()
+        .find(|voice| voice.short_name == short_name)
+}
+
+pub fn get_voices_list_names() -> Vec<(String, String)> {
+    load_voices()
+        .into_iter()
+        .map(|voice| (voice.short_name, voice.locale))
+        .collect::<Vec<(String, String)>>()
+}
+
+fn matches_field(value: &str, filter: &Option<String>) -> bool {
+    match filter {
+        Some(filter) if !filter.is_empty() => value.eq_ignore_ascii_case(filter),
+        _ => true,
+    }
+}
+
+pub fn search_voices(query: &VoiceQuery) -> Vec<VoiceInfo> {
+    let text = query
+        .text
+        .as_ref()
+        .map(|text| text.trim().to_lowercase())
+        .unwrap_or_default();
+
+    load_voices()
+        .into_iter()
+        .filter(|voice| matches_field(&voice.locale, &query.locale))
+        .filter(|voice| matches_field(&voice.language, &query.language))
+        .filter(|voice| matches_field(&voice.gender, &query.gender))
+        .filter(|voice| {
+            if text.is_empty() {
+                return true;
+            }
+
+            [
+                &voice.short_name,
+                &voice.friendly_name,
+                &voice.locale,
+                &voice.gender,
+            ]
+            .iter()
+            .any(|field| field.to_lowercase().contains(&text))
+                || voice
+                    .styles
+                    .iter()
+                    .chain(voice.roles.iter())
+                    .any(|tag| tag.to_lowercase().contains(&text))
+        })
+        .collect()
+}
@@ -1 +1,7 @@
 pub const WINDOW_LABEL: &str = "main";
+// Secrets are referenced as secret://<id> wherever a value would be stored
+pub const SECRET_PREFIX: &str = "secret://";
+pub const SECRETS_FILE_NAME: &str = "secrets.json";
+pub const SECRETS_SALT_FILE_NAME: &str = "secrets.salt";
+pub const KEYRING_SERVICE: &str = "tts.tihomir-selak.from.hr";
+pub const REDACTED: &str = "******";
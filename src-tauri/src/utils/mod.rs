@@ -1,2 +1,3 @@
 pub(crate) mod constants;
+pub(crate) mod secrets;
 pub(crate) mod structs;
@@ -0,0 +1,301 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::scripts::disk::write_atomically;
+use crate::tts::constants::CONFIG_FOLDER_NAME;
+use crate::utils::constants::{
+    KEYRING_SERVICE, REDACTED, SECRETS_FILE_NAME, SECRETS_SALT_FILE_NAME, SECRET_PREFIX,
+};
+
+type SecretResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+lazy_static::lazy_static! {
+    static ref SECRETS_LOCK: Mutex<()> = Mutex::new(());
+    static ref SECRET_REFERENCE: Regex = Regex::new(&format!(
+        r"{}([A-Za-z0-9_.-]+)",
+        regex::escape(SECRET_PREFIX)
+    ))
+    .unwrap();
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct StoredSecret {
+    label: String,
+    // Stored in the OS keyring, `value` is empty then
+    keyring: bool,
+    // Base64 of the nonce followed by the AES-GCM ciphertext
+    #[serde(default)]
+    value: Option<String>,
+}
+
+// What the window sees, the value itself never leaves the backend
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SecretInfo {
+    pub id: String,
+    pub label: String,
+    pub reference: String,
+    pub keyring: bool,
+}
+
+fn get_config_path(file_name: &str) -> PathBuf {
+    let mut path = PathBuf::from(CONFIG_FOLDER_NAME);
+    path.push(file_name);
+    path
+}
+
+fn load_secrets() -> HashMap<String, StoredSecret> {
+    let path = get_config_path(SECRETS_FILE_NAME);
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open secrets file: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    match serde_json::from_reader(file) {
+        Ok(secrets) => secrets,
+        Err(e) => {
+            eprintln!("Failed to parse secrets file: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+fn save_secrets(secrets: &HashMap<String, StoredSecret>) -> SecretResult<()> {
+    let path = get_config_path(SECRETS_FILE_NAME);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(secrets)?;
+    fs::write(path, json)?;
+
+    Ok(())
+}
+
+// Only a missing salt is created, and only for a new secret. Replacing one that could
+// not be read would make every secret already on disk impossible to decrypt.
+fn load_salt(create: bool) -> SecretResult<Vec<u8>> {
+    let salt_path = get_config_path(SECRETS_SALT_FILE_NAME);
+    match fs::read(&salt_path) {
+        Ok(salt) if !salt.is_empty() => Ok(salt),
+        Ok(_) => Err(format!("Secrets salt {} is empty", salt_path.display()).into()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound && create => {
+            let mut salt = vec![0u8; 32];
+            OsRng.fill_bytes(&mut salt);
+            if let Some(parent) = salt_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_atomically(&salt_path, &salt)?;
+            Ok(salt)
+        }
+        Err(e) => Err(format!("Failed to read secrets salt {}: {}", salt_path.display(), e).into()),
+    }
+}
+
+// The key is derived from a random salt created on first use together with the
+// user and machine names, so a copied config folder does not decrypt elsewhere.
+// Everything it is built from is readable by the same user, so this only keeps
+// values out of plain sight, the OS keyring is what actually protects them.
+fn derive_key(create_salt: bool) -> SecretResult<[u8; 32]> {
+    let salt = load_salt(create_salt)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&salt);
+    hasher.update(whoami::username().as_bytes());
+    hasher.update(whoami::devicename().as_bytes());
+
+    Ok(hasher.finalize().into())
+}
+
+fn encrypt(value: &str) -> SecretResult<String> {
+    let key = derive_key(true)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, value.as_bytes())
+        .map_err(|_| "Failed to encrypt secret")?;
+
+    let mut data = nonce.to_vec();
+    data.extend(ciphertext);
+
+    Ok(STANDARD.encode(data))
+}
+
+fn decrypt(value: &str) -> SecretResult<String> {
+    let data = STANDARD.decode(value)?;
+    if data.len() < 12 {
+        return Err("Stored secret is corrupt".into());
+    }
+
+    let key = derive_key(false)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let (nonce, ciphertext) = data.split_at(12);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt secret, it was stored on another machine or user")?;
+
+    Ok(String::from_utf8(plaintext)?)
+}
+
+fn keyring_entry(id: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, id)
+}
+
+fn new_secret_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    format!("{:x}", nanos)
+}
+
+// Random token for the API server, hex encoded
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Returns the id when the value is a secret://<id> reference
+pub fn secret_id(value: &str) -> Option<&str> {
+    value
+        .strip_prefix(SECRET_PREFIX)
+        .filter(|id| !id.is_empty())
+}
+
+pub fn is_secret_reference(value: &str) -> bool {
+    secret_id(value).is_some()
+}
+
+// Stores the value in the keyring, or encrypted on disk when there is none,
+// and returns the reference to save in its place
+pub fn save_secret(label: &str, value: &str, id: Option<&str>) -> SecretResult<String> {
+    let _lock = SECRETS_LOCK.lock().unwrap();
+    let mut secrets = load_secrets();
+    let id = id
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_string())
+        .unwrap_or_else(new_secret_id);
+
+    let stored = match keyring_entry(&id).and_then(|entry| entry.set_password(value)) {
+        Ok(_) => StoredSecret {
+            label: label.to_string(),
+            keyring: true,
+            value: None,
+        },
+        Err(e) => {
+            println!(
+                "Keyring unavailable, encrypting secret {} on disk ({})",
+                id, e
+            );
+            StoredSecret {
+                label: label.to_string(),
+                keyring: false,
+                value: Some(encrypt(value)?),
+            }
+        }
+    };
+
+    secrets.insert(id.clone(), stored);
+    save_secrets(&secrets)?;
+
+    Ok(format!("{}{}", SECRET_PREFIX, id))
+}
+
+pub fn remove_secret(id: &str) -> SecretResult<bool> {
+    let id = secret_id(id).unwrap_or(id);
+    let _lock = SECRETS_LOCK.lock().unwrap();
+    let mut secrets = load_secrets();
+
+    let stored = match secrets.remove(id) {
+        Some(stored) => stored,
+        None => return Ok(false),
+    };
+
+    if stored.keyring {
+        if let Err(e) = keyring_entry(id).and_then(|entry| entry.delete_credential()) {
+            println!("Failed to remove secret {} from keyring ({})", id, e);
+        }
+    }
+
+    save_secrets(&secrets)?;
+
+    Ok(true)
+}
+
+pub fn list_secrets() -> Vec<SecretInfo> {
+    let mut secrets = load_secrets()
+        .into_iter()
+        .map(|(id, stored)| SecretInfo {
+            reference: format!("{}{}", SECRET_PREFIX, id),
+            id,
+            label: stored.label,
+            keyring: stored.keyring,
+        })
+        .collect::<Vec<SecretInfo>>();
+    secrets.sort_by(|a, b| a.label.cmp(&b.label));
+    secrets
+}
+
+fn get_secret(id: &str) -> SecretResult<String> {
+    let stored = load_secrets()
+        .remove(id)
+        .ok_or_else(|| format!("Secret {} not found", id))?;
+
+    if stored.keyring {
+        return Ok(keyring_entry(id)?.get_password()?);
+    }
+
+    match stored.value {
+        Some(value) => decrypt(&value),
+        None => Err(format!("Secret {} has no value", id).into()),
+    }
+}
+
+// Replaces every secret://<id> in the text with its value, the resolved values
+// are pushed to `resolved` so output can be redacted afterwards
+pub fn resolve_secrets(text: &str, resolved: &mut Vec<String>) -> SecretResult<String> {
+    if !SECRET_REFERENCE.is_match(text) {
+        return Ok(text.to_string());
+    }
+
+    let mut result = String::new();
+    let mut last = 0;
+    for captures in SECRET_REFERENCE.captures_iter(text) {
+        let matched = captures.get(0).unwrap();
+        let value = get_secret(&captures[1])?;
+        result.push_str(&text[last..matched.start()]);
+        result.push_str(&value);
+        if !value.is_empty() && !resolved.contains(&value) {
+            resolved.push(value);
+        }
+        last = matched.end();
+    }
+    result.push_str(&text[last..]);
+
+    Ok(result)
+}
+
+// Masks resolved secret values before text is logged or sent to the window
+pub fn redact(text: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret, REDACTED)
+    })
+}
@@ -51,6 +51,26 @@ export const ApiTester: Component = () => {
   // Initialize presets from local storage
   loadPresetsFromStorage();
 
+  const SECRET_PREFIX = "secret://";
+  const SENSITIVE_HEADER = /authorization|token|api[-_]?key|secret|cookie|password/i;
+
+  // Sensitive header values go to the backend secret store, presets only keep the reference
+  const protectHeaders = async (presetName: string, values: KeyValuePair[]) =>
+    Promise.all(
+      values.map(async ({ key, value }) => {
+        if (!key || !value || !SENSITIVE_HEADER.test(key) || value.includes(SECRET_PREFIX)) {
+          return { key, value };
+        }
+        const reference = await invoke<string>("save_secret", {
+          label: `${presetName} ${key}`,
+          value,
+        });
+        return { key, value: reference };
+      }),
+    );
+
+  const usesSecrets = () => headers().some(({ value }) => value.includes(SECRET_PREFIX));
+
   const handleSubmit = async (e: Event) => {
     e.preventDefault();
     console.log("Form submitted");
@@ -80,7 +100,8 @@ export const ApiTester: Component = () => {
         options["body"] = bodyContent;
       }
 
-      if (useBackend()) {
+      // Only the backend can resolve secret references
+      if (useBackend() || usesSecrets()) {
         console.log("Using Rust backend");
         const result = await invoke("make_api_request", {
           url: url(),
@@ -105,12 +126,14 @@ export const ApiTester: Component = () => {
     }
   };
 
-  const savePreset = (name: string) => {
+  const savePreset = async (name: string) => {
+    const protectedHeaders = await protectHeaders(name, headers());
+    setHeaders(protectedHeaders);
     const newPreset: Preset = {
       name,
       url: url(),
       method: method(),
-      headers: headers(),
+      headers: protectedHeaders,
       body: body(),
       bodyType: bodyType(),
       rawBody: rawBody(),
@@ -138,12 +161,14 @@ export const ApiTester: Component = () => {
     savePresetsToStorage();
   };
 
-  const updatePreset = (preset: Preset) => {
+  const updatePreset = async (preset: Preset) => {
+    const protectedHeaders = await protectHeaders(preset.name, headers());
+    setHeaders(protectedHeaders);
     const updatedPreset: Preset = {
       ...preset,
       url: url(),
       method: method(),
-      headers: headers(),
+      headers: protectedHeaders,
       body: body(),
       bodyType: bodyType(),
       rawBody: rawBody(),
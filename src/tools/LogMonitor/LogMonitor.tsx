@@ -1,29 +1,194 @@
-import { createSignal } from "solid-js";
-import { Box, Typography, TextField, Button } from "@suid/material";
+import { createEffect, createSignal, For } from "solid-js";
+import {
+  Box,
+  Typography,
+  TextField,
+  Button,
+  IconButton,
+  MenuItem,
+  Select,
+  Tab,
+  Tabs,
+} from "@suid/material";
+import StopIcon from "@suid/icons-material/Stop";
 
 import { styled } from "solid-styled-components";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { get, isEmpty } from "lodash";
+import { LogRules } from "./LogRules";
+import { LogSearch } from "./LogSearch";
 
 const Container = styled(Box)`
   padding: 16px;
 `;
 
+const LogView = styled("pre")`
+  height: 60vh;
+  overflow-y: auto;
+  margin: 8px 0 0;
+  padding: 8px;
+  font-size: 12px;
+  white-space: pre-wrap;
+  word-break: break-all;
+  background: rgba(0, 0, 0, 0.05);
+`;
+
+// Lines kept per file, older ones are dropped from the view
+const MAX_VIEW_LINES = 5000;
+
+interface LogLine {
+  text: string;
+  level?: string;
+  timestamp?: number;
+  message?: string;
+  fields: Record<string, string>;
+  highlight?: string;
+}
+
+// "key=value" and "level:error" terms match parsed fields, anything else is searched in the line
+const matchesFilter = (line: LogLine, filter: string) =>
+  filter
+    .split(/\s+/)
+    .filter((term) => !isEmpty(term))
+    .every((term) => {
+      const fieldMatch = term.match(/^([\w.@-]+)=(.*)$/);
+      if (fieldMatch) {
+        return get(line.fields, [fieldMatch[1]], "") === fieldMatch[2];
+      }
+      if (term.startsWith("level:")) {
+        return line.level === term.slice("level:".length).toLowerCase();
+      }
+      return line.text.toLowerCase().includes(term.toLowerCase());
+    });
+
+const LEVEL_ORDER = ["trace", "debug", "info", "warn", "error", "fatal"];
+
+// "timestamp", "level" and "message" sort on the parsed parts, any other name on that field
+const sortValue = (line: LogLine, sortBy: string): number | string | undefined => {
+  if (sortBy === "timestamp") {
+    return line.timestamp;
+  }
+  if (sortBy === "level") {
+    const rank = LEVEL_ORDER.indexOf(line.level ?? "");
+    return rank === -1 ? undefined : rank;
+  }
+  if (sortBy === "message") {
+    return line.message;
+  }
+  return get(line.fields, [sortBy]);
+};
+
+// Numbers compare as numbers, lines without the value go last in either direction
+const compareLines = (a: LogLine, b: LogLine, sortBy: string, descending: boolean) => {
+  const left = sortValue(a, sortBy);
+  const right = sortValue(b, sortBy);
+  if (left === undefined || right === undefined) {
+    return (left === undefined ? 1 : 0) - (right === undefined ? 1 : 0);
+  }
+
+  const leftNumber = Number(left);
+  const rightNumber = Number(right);
+  const order =
+    !isNaN(leftNumber) && !isNaN(rightNumber) && left !== "" && right !== ""
+      ? leftNumber - rightNumber
+      : String(left).localeCompare(String(right));
+  return descending ? -order : order;
+};
+
 export const LogMonitor = () => {
   const [filePath, setFilePath] = createSignal("");
+  const [tails, setTails] = createSignal<string[]>([]);
+  const [selected, setSelected] = createSignal(0);
+  const [lines, setLines] = createSignal<Record<string, LogLine[]>>({});
+  const [statuses, setStatuses] = createSignal<Record<string, string>>({});
+  const [formats, setFormats] = createSignal<Record<string, string>>({});
+  const [filter, setFilter] = createSignal("");
+  // An empty sort keeps the file order
+  const [sortBy, setSortBy] = createSignal("");
+  const [descending, setDescending] = createSignal(true);
 
-  const handleInputChange = (value: string) => {
-    setFilePath(value);
+  const refreshTails = async () => {
+    setTails(await invoke<string[]>("get_log_tails"));
   };
 
-  const openFileInPowerShell = () => {
-    if (filePath()) {
-      invoke("monitor_log_file", {
-        filepath: filePath(),
-      });
-    } else {
+  createEffect(async () => {
+    const unlistenLines = await listen("log_lines", (event: any) => {
+      const path = get(event, ["payload", "path"], "");
+      const batch = get(event, ["payload", "lines"], []);
+      setFormats((state) => ({ ...state, [path]: get(event, ["payload", "format"], "plain") }));
+      setLines((state) => ({
+        ...state,
+        [path]: [...get(state, [path], []), ...batch].slice(-MAX_VIEW_LINES),
+      }));
+      // Lets the tail read the next batch
+      invoke("ack_log_lines", { path });
+    });
+    const unlistenStatus = await listen("log_tail_status", (event: any) => {
+      const path = get(event, ["payload", "path"], "");
+      const status = get(event, ["payload", "status"], "");
+      setStatuses((state) => ({ ...state, [path]: status }));
+    });
+    refreshTails();
+
+    return () => {
+      unlistenLines();
+      unlistenStatus();
+    };
+  });
+
+  const startTail = async () => {
+    if (!filePath()) {
       alert("Please enter a file path.");
+      return;
+    }
+
+    try {
+      await invoke("start_log_tail", { path: filePath() });
+      await refreshTails();
+      setSelected(Math.max(tails().indexOf(filePath()), 0));
+    } catch (error) {
+      alert(error);
+    }
+  };
+
+  const stopTail = async (path: string) => {
+    await invoke("stop_log_tail", { path });
+    await refreshTails();
+    setSelected(0);
+  };
+
+  const selectedPath = () => get(tails(), [selected()], "");
+
+  // Field names of the selected file's lines, offered next to the parsed parts
+  const sortFields = () => {
+    const names = new Set<string>();
+    get(lines(), [selectedPath()], []).forEach((line: LogLine) =>
+      Object.keys(line.fields).forEach((name) => names.add(name)),
+    );
+    return [...names].sort();
+  };
+
+  // The slice that is shown is also the slice that gets exported
+  const visibleLines = () => {
+    const filtered = get(lines(), [selectedPath()], []).filter((line: LogLine) =>
+      matchesFilter(line, filter()),
+    );
+    if (isEmpty(sortBy())) {
+      return filtered;
     }
+    return [...filtered].sort((a, b) => compareLines(a, b, sortBy(), descending()));
   };
+
+  const exportLines = async (format: "csv" | "json") => {
+    try {
+      const path = await invoke<string>("export_log_lines", { lines: visibleLines(), format });
+      alert(`Exported to ${path}`);
+    } catch (error) {
+      alert(error);
+    }
+  };
+
   return (
     <Container>
       <Typography variant="h4" sx={{ mb: "8px" }}>
@@ -32,18 +197,71 @@ export const LogMonitor = () => {
       <TextField
         label="Enter log file path"
         value={filePath()}
-        onChange={(e) => handleInputChange(e.target.value)}
+        onChange={(e) => setFilePath(e.target.value)}
         fullWidth
       />
-      <Button
-        variant="contained"
-        color="primary"
-        fullWidth
-        onClick={openFileInPowerShell}
-        sx={{ mt: "8px" }}
-      >
+      <Button variant="contained" color="primary" fullWidth onClick={startTail} sx={{ mt: "8px" }}>
         Monitor Log File
       </Button>
+      {!isEmpty(filePath()) && <LogSearch path={filePath()} />}
+      {!isEmpty(tails()) && (
+        <Box sx={{ mt: "16px" }}>
+          <Tabs value={selected()} onChange={(_, value) => setSelected(value)}>
+            <For each={tails()}>{(path) => <Tab label={path.split(/[\\/]/).pop()} />}</For>
+          </Tabs>
+          <Box sx={{ display: "flex", alignItems: "center", gap: "8px", mt: "8px" }}>
+            <Typography variant="body2" sx={{ flex: 1 }}>
+              {selectedPath()} {get(statuses(), [selectedPath()], "")}{" "}
+              {get(formats(), [selectedPath()], "")}
+            </Typography>
+            <IconButton color="secondary" onClick={() => stopTail(selectedPath())}>
+              <StopIcon />
+            </IconButton>
+          </Box>
+          <Box sx={{ display: "flex", alignItems: "center", gap: "8px", mt: "8px" }}>
+            <TextField
+              size="small"
+              label="Filter (text, key=value, level:error)"
+              value={filter()}
+              onChange={(e) => setFilter(e.target.value)}
+              sx={{ flex: 1 }}
+            />
+            <Select
+              size="small"
+              value={sortBy()}
+              displayEmpty
+              onChange={(event) => setSortBy(event.target.value)}
+            >
+              <MenuItem value="">File order</MenuItem>
+              <MenuItem value="timestamp">Time</MenuItem>
+              <MenuItem value="level">Level</MenuItem>
+              <MenuItem value="message">Message</MenuItem>
+              <For each={sortFields()}>{(name) => <MenuItem value={name}>{name}</MenuItem>}</For>
+            </Select>
+            <Button
+              variant="outlined"
+              disabled={isEmpty(sortBy())}
+              onClick={() => setDescending(!descending())}
+            >
+              {descending() ? "Descending" : "Ascending"}
+            </Button>
+            <Button variant="outlined" onClick={() => exportLines("csv")}>
+              CSV
+            </Button>
+            <Button variant="outlined" onClick={() => exportLines("json")}>
+              JSON
+            </Button>
+          </Box>
+          <LogView>
+            <For each={visibleLines()}>
+              {(line: LogLine) => (
+                <div style={{ background: line.highlight ?? "transparent" }}>{line.text}</div>
+              )}
+            </For>
+          </LogView>
+          <LogRules path={selectedPath()} />
+        </Box>
+      )}
     </Container>
   );
 };
@@ -0,0 +1,213 @@
+import { createEffect, createSignal, For } from "solid-js";
+import {
+  Box,
+  Button,
+  Checkbox,
+  FormControlLabel,
+  IconButton,
+  MenuItem,
+  Select,
+  TextField,
+  Typography,
+} from "@suid/material";
+import DeleteIcon from "@suid/icons-material/Delete";
+import { invoke } from "@tauri-apps/api/core";
+import { get, isEmpty } from "lodash";
+
+interface LogRule {
+  name: string;
+  action: "include" | "exclude" | "highlight" | "alert";
+  pattern?: string;
+  field?: string;
+  case_insensitive: boolean;
+  levels: string[];
+  since?: string;
+  until?: string;
+  color?: string;
+  alerts: Array<{ type: "notification" | "speak" | "webhook"; url?: string }>;
+  cooldown_secs: number;
+  enabled: boolean;
+}
+
+const newRule = (): LogRule => ({
+  name: "",
+  action: "include",
+  pattern: "",
+  case_insensitive: true,
+  levels: [],
+  alerts: [],
+  cooldown_secs: 30,
+  enabled: true,
+});
+
+const hasAlert = (rule: LogRule, type: string) => rule.alerts.some((alert) => alert.type === type);
+
+export const LogRules = (props: { path: string }) => {
+  const [rules, setRules] = createSignal<LogRule[]>([]);
+  const [error, setError] = createSignal("");
+
+  createEffect(async () => {
+    if (props.path) {
+      setRules(await invoke<LogRule[]>("get_log_rules", { path: props.path }));
+    }
+  });
+
+  const updateRule = (index: number, changes: Partial<LogRule>) => {
+    setRules((state) => state.map((rule, i) => (i === index ? { ...rule, ...changes } : rule)));
+  };
+
+  const toggleAlert = (index: number, type: "notification" | "speak") => {
+    const rule = rules()[index];
+    const alerts = hasAlert(rule, type)
+      ? rule.alerts.filter((alert) => alert.type !== type)
+      : [...rule.alerts, { type }];
+    updateRule(index, { alerts });
+  };
+
+  const setWebhook = (index: number, url: string) => {
+    const alerts = rules()[index].alerts.filter((alert) => alert.type !== "webhook");
+    updateRule(index, { alerts: isEmpty(url) ? alerts : [...alerts, { type: "webhook", url }] });
+  };
+
+  const saveRules = async () => {
+    try {
+      await invoke("save_log_rules", { path: props.path, rules: rules() });
+      setError("");
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  return (
+    <Box sx={{ mt: "16px" }}>
+      <Typography variant="h6">Rules</Typography>
+      <For each={rules()}>
+        {(rule, index) => (
+          <Box sx={{ display: "flex", flexWrap: "wrap", alignItems: "center", gap: "8px", mt: "8px" }}>
+            <Select
+              size="small"
+              value={rule.action}
+              onChange={(event) => updateRule(index(), { action: event.target.value })}
+            >
+              <MenuItem value="include">Include</MenuItem>
+              <MenuItem value="exclude">Exclude</MenuItem>
+              <MenuItem value="highlight">Highlight</MenuItem>
+              <MenuItem value="alert">Alert</MenuItem>
+            </Select>
+            <TextField
+              size="small"
+              label="Name"
+              value={rule.name}
+              onChange={(event) => updateRule(index(), { name: event.target.value })}
+            />
+            <TextField
+              size="small"
+              label="Regex"
+              value={get(rule, ["pattern"], "")}
+              onChange={(event) => updateRule(index(), { pattern: event.target.value })}
+            />
+            <TextField
+              size="small"
+              label="Field"
+              value={get(rule, ["field"], "")}
+              onChange={(event) => updateRule(index(), { field: event.target.value })}
+            />
+            <TextField
+              size="small"
+              label="Levels"
+              value={rule.levels.join(",")}
+              onChange={(event) =>
+                updateRule(index(), {
+                  levels: event.target.value
+                    .split(",")
+                    .map((level) => level.trim())
+                    .filter((level) => !isEmpty(level)),
+                })
+              }
+            />
+            <TextField
+              size="small"
+              label="Since"
+              value={get(rule, ["since"], "")}
+              onChange={(event) => updateRule(index(), { since: event.target.value })}
+            />
+            <TextField
+              size="small"
+              label="Until"
+              value={get(rule, ["until"], "")}
+              onChange={(event) => updateRule(index(), { until: event.target.value })}
+            />
+            {rule.action === "highlight" && (
+              <TextField
+                size="small"
+                label="Color"
+                value={get(rule, ["color"], "")}
+                onChange={(event) => updateRule(index(), { color: event.target.value })}
+              />
+            )}
+            {rule.action === "alert" && (
+              <>
+                <FormControlLabel
+                  control={
+                    <Checkbox
+                      checked={hasAlert(rule, "notification")}
+                      onChange={() => toggleAlert(index(), "notification")}
+                    />
+                  }
+                  label="Notify"
+                />
+                <FormControlLabel
+                  control={
+                    <Checkbox
+                      checked={hasAlert(rule, "speak")}
+                      onChange={() => toggleAlert(index(), "speak")}
+                    />
+                  }
+                  label="Speak"
+                />
+                <TextField
+                  size="small"
+                  label="Webhook URL"
+                  value={get(
+                    rule.alerts.find((alert) => alert.type === "webhook"),
+                    ["url"],
+                    "",
+                  )}
+                  onChange={(event) => setWebhook(index(), event.target.value)}
+                />
+              </>
+            )}
+            <FormControlLabel
+              control={
+                <Checkbox
+                  checked={rule.enabled}
+                  onChange={() => updateRule(index(), { enabled: !rule.enabled })}
+                />
+              }
+              label="Enabled"
+            />
+            <IconButton
+              color="error"
+              onClick={() => setRules((state) => state.filter((_, i) => i !== index()))}
+            >
+              <DeleteIcon />
+            </IconButton>
+          </Box>
+        )}
+      </For>
+      {!isEmpty(error()) && (
+        <Typography color="error" sx={{ mt: "8px" }}>
+          {error()}
+        </Typography>
+      )}
+      <Box sx={{ display: "flex", gap: "8px", mt: "8px" }}>
+        <Button variant="outlined" onClick={() => setRules((state) => [...state, newRule()])}>
+          Add Rule
+        </Button>
+        <Button variant="contained" onClick={saveRules}>
+          Save Rules
+        </Button>
+      </Box>
+    </Box>
+  );
+};
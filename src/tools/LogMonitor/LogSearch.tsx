@@ -0,0 +1,152 @@
+import { createEffect, createSignal, For } from "solid-js";
+import { Box, Button, Checkbox, FormControlLabel, TextField, Typography } from "@suid/material";
+import { styled } from "solid-styled-components";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { get, isEmpty } from "lodash";
+
+const ResultView = styled("pre")`
+  max-height: 40vh;
+  overflow-y: auto;
+  margin: 8px 0 0;
+  padding: 8px;
+  font-size: 12px;
+  white-space: pre-wrap;
+  word-break: break-all;
+  background: rgba(0, 0, 0, 0.05);
+`;
+
+interface SearchMatch {
+  file: string;
+  line_number: number;
+  line: string;
+  before: string[];
+  after: string[];
+}
+
+export const LogSearch = (props: { path: string }) => {
+  const [query, setQuery] = createSignal("");
+  const [regex, setRegex] = createSignal(false);
+  const [caseInsensitive, setCaseInsensitive] = createSignal(true);
+  const [includeRotated, setIncludeRotated] = createSignal(true);
+  const [searchId, setSearchId] = createSignal("");
+  const [running, setRunning] = createSignal(false);
+  const [status, setStatus] = createSignal("");
+  const [matches, setMatches] = createSignal<SearchMatch[]>([]);
+
+  createEffect(async () => {
+    const unlistenResults = await listen("log_search_results", (event: any) => {
+      if (get(event, ["payload", "search_id"]) !== searchId()) {
+        return;
+      }
+      setMatches((state) => [...state, ...get(event, ["payload", "matches"], [])]);
+    });
+    const unlistenStatus = await listen("log_search_status", (event: any) => {
+      if (get(event, ["payload", "search_id"]) !== searchId()) {
+        return;
+      }
+      const searchStatus = get(event, ["payload", "status"], "");
+      const file = get(event, ["payload", "file"], "");
+      const message = get(event, ["payload", "message"], "");
+      setStatus([searchStatus, file, message].filter((part) => !isEmpty(part)).join(" "));
+      if (["done", "cancelled", "limit_reached"].includes(searchStatus)) {
+        setRunning(false);
+      }
+    });
+
+    return () => {
+      unlistenResults();
+      unlistenStatus();
+    };
+  });
+
+  const startSearch = async () => {
+    if (running()) {
+      await invoke("cancel_log_search", { searchId: searchId() });
+    }
+
+    setMatches([]);
+    setStatus("");
+    try {
+      const id = await invoke<string>("search_log", {
+        search: {
+          path: props.path,
+          query: query(),
+          regex: regex(),
+          case_insensitive: caseInsensitive(),
+          include_rotated: includeRotated(),
+        },
+      });
+      setSearchId(id);
+      setRunning(true);
+    } catch (error) {
+      setStatus(String(error));
+    }
+  };
+
+  const cancelSearch = async () => {
+    await invoke("cancel_log_search", { searchId: searchId() });
+  };
+
+  return (
+    <Box sx={{ mt: "16px" }}>
+      <Typography variant="h6">Search</Typography>
+      <Box sx={{ display: "flex", flexWrap: "wrap", alignItems: "center", gap: "8px", mt: "8px" }}>
+        <TextField
+          size="small"
+          label="Search"
+          value={query()}
+          onChange={(event) => setQuery(event.target.value)}
+          sx={{ flex: 1 }}
+        />
+        <FormControlLabel
+          control={<Checkbox checked={regex()} onChange={() => setRegex(!regex())} />}
+          label="Regex"
+        />
+        <FormControlLabel
+          control={
+            <Checkbox
+              checked={caseInsensitive()}
+              onChange={() => setCaseInsensitive(!caseInsensitive())}
+            />
+          }
+          label="Ignore case"
+        />
+        <FormControlLabel
+          control={
+            <Checkbox
+              checked={includeRotated()}
+              onChange={() => setIncludeRotated(!includeRotated())}
+            />
+          }
+          label="Rotated files"
+        />
+        <Button variant="contained" onClick={startSearch} disabled={isEmpty(query())}>
+          Search
+        </Button>
+        <Button variant="outlined" color="secondary" onClick={cancelSearch} disabled={!running()}>
+          Cancel
+        </Button>
+      </Box>
+      <Typography variant="body2" sx={{ mt: "8px" }}>
+        {status()} {!isEmpty(matches()) && `${matches().length} matches`}
+      </Typography>
+      {!isEmpty(matches()) && (
+        <ResultView>
+          <For each={matches()}>
+            {(match) => (
+              <Box sx={{ mb: "8px" }}>
+                <div style={{ opacity: 0.6 }}>
+                  {match.file.split(/[\\/]/).pop()}:{match.line_number}
+                </div>
+                <For each={match.before}>{(line) => <div style={{ opacity: 0.6 }}>{line}</div>}</For>
+                <div style={{ "font-weight": "bold" }}>{match.line}</div>
+                <For each={match.after}>{(line) => <div style={{ opacity: 0.6 }}>{line}</div>}</For>
+              </Box>
+            )}
+          </For>
+        </ResultView>
+      )}
+    </Box>
+  );
+};
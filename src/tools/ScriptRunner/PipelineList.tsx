@@ -0,0 +1,60 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { get, isEmpty, map } from "lodash";
+import { createEffect, createSignal } from "solid-js";
+import { Box, IconButton, List, ListItem, Typography } from "@suid/material";
+import PlayArrowIcon from "@suid/icons-material/PlayArrow";
+
+interface Pipeline {
+  name: string;
+  mode: "sequential" | "parallel";
+  steps: { script_name: string }[];
+}
+
+export const PipelineList = () => {
+  const [pipelines, setPipelines] = createSignal<Pipeline[]>([]);
+  const [statuses, setStatuses] = createSignal<Record<string, string>>({});
+
+  createEffect(async () => {
+    setPipelines(await invoke<Pipeline[]>("get_pipelines"));
+
+    const unlisten = await listen("pipeline_status", (event: any) => {
+      const run = get(event, ["payload"], {});
+      setStatuses({ ...statuses(), [get(run, ["pipeline"], "")]: get(run, ["status"], "") });
+    });
+
+    return () => {
+      unlisten();
+    };
+  });
+
+  return (
+    <>
+      {!isEmpty(pipelines()) && (
+        <Box sx={{ mt: 3 }}>
+          <Typography variant="h6">Pipelines</Typography>
+          <List>
+            {map(pipelines(), (pipeline) => (
+              <ListItem
+                disablePadding
+                secondaryAction={
+                  <IconButton
+                    color="primary"
+                    onClick={() => invoke("run_pipeline", { name: pipeline.name })}
+                  >
+                    <PlayArrowIcon />
+                  </IconButton>
+                }
+              >
+                <Typography>
+                  {pipeline.name} ({pipeline.mode}, {pipeline.steps.length} steps)
+                  {get(statuses(), [pipeline.name]) && ` - ${get(statuses(), [pipeline.name])}`}
+                </Typography>
+              </ListItem>
+            ))}
+          </List>
+        </Box>
+      )}
+    </>
+  );
+};
@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/core";
 import { emit, listen } from "@tauri-apps/api/event";
 import { get, isEmpty, join, map } from "lodash";
 import { createEffect, createSignal } from "solid-js";
@@ -18,6 +19,7 @@ import DeleteIcon from "@suid/icons-material/Delete";
 import FileCopy from "@suid/icons-material/FileCopy";
 import AddIcon from "@suid/icons-material/Add";
 import { AddScriptModal } from "./AddScriptModal";
+import { PipelineList } from "./PipelineList";
 import { ScriptInfo } from "./types";
 import { FolderPathButton } from "../../components/inputs/FolderPathButton";
 import { VISIBILITY } from "./constants";
@@ -30,6 +32,23 @@ export const ScriptRunner = () => {
   const [scriptInfos, setScriptInfos] = createSignal<ScriptInfo>([]);
   const [addScriptModalOpen, setAddScriptModalOpen] = createSignal(false);
   const [scriptVariables, setScriptVariables] = createSignal({});
+  const [scriptsError, setScriptsError] = createSignal("");
+  const [integrity, setIntegrity] = createSignal<Record<string, any>>({});
+
+  const loadIntegrity = async () => {
+    const reports = await invoke<any[]>("get_script_integrity");
+    setIntegrity(Object.fromEntries(map(reports, (report) => [report.name, report])));
+  };
+
+  createEffect(async () => {
+    const unlisten = await listen("scripts_error", (event: any) => {
+      setScriptsError(get(event, ["payload", "message"], ""));
+    });
+
+    return () => {
+      unlisten();
+    };
+  });
 
   createEffect(async () => {
     const unlisten = await listen("scripts", (event: any) => {
@@ -42,6 +61,64 @@ export const ScriptRunner = () => {
       }));
 
       setScriptInfos(scriptVariables);
+      loadIntegrity();
+    });
+
+    return () => {
+      unlisten();
+    };
+  });
+
+  createEffect(async () => {
+    const unlisten = await listen("script_prompt_required", (event: any) => {
+      const request = get(event, ["payload"], {});
+      const name = get(request, ["name"], "");
+      const promptValues = {};
+      for (const label of get(request, ["labels"], [])) {
+        const answer = window.prompt(label);
+        if (answer === null) {
+          return;
+        }
+        promptValues[label] = answer;
+      }
+
+      const script = scriptInfos().find((info) => info.name === name);
+      emit("start_script", {
+        visibility: get(scriptsHidden(), [name], false) ? VISIBILITY.HIDDEN : VISIBILITY.VISIBILE,
+        arguments: join(get(scriptVariables(), [name], []), " "),
+        values: map(get(script, ["args"], []), (_, index) =>
+          get(scriptVariables(), [name, index], null),
+        ),
+        prompt_values: promptValues,
+        path: get(request, ["path"], ""),
+        name,
+      });
+    });
+
+    return () => {
+      unlisten();
+    };
+  });
+
+  createEffect(async () => {
+    const unlisten = await listen("script_changed", (event: any) => {
+      const request = get(event, ["payload"], {});
+      const name = get(request, ["name"], "");
+      if (!window.confirm(`${get(request, ["message"], "")}\n\nRun it anyway?`)) {
+        return;
+      }
+
+      const script = scriptInfos().find((info) => info.name === name);
+      emit("start_script", {
+        visibility: get(scriptsHidden(), [name], false) ? VISIBILITY.HIDDEN : VISIBILITY.VISIBILE,
+        arguments: join(get(scriptVariables(), [name], []), " "),
+        values: map(get(script, ["args"], []), (_, index) =>
+          get(scriptVariables(), [name, index], null),
+        ),
+        path: get(request, ["path"], ""),
+        name,
+        allow_changed: true,
+      });
     });
 
     return () => {
@@ -56,6 +133,11 @@ export const ScriptRunner = () => {
 
   return (
     <Box sx={{ p: 3 }}>
+      {!isEmpty(scriptsError()) && (
+        <Typography color="error" sx={{ mb: 2 }} onClick={() => setScriptsError("")}>
+          {scriptsError()}
+        </Typography>
+      )}
       <ScrollContainer>
         {map(scriptInfos(), (values) => (
           <ScriptCard elevation={3}>
@@ -63,6 +145,30 @@ export const ScriptRunner = () => {
               <Typography variant="h6" gutterBottom sx={{ fontWeight: "bold" }}>
                 {values.name}
               </Typography>
+              {get(integrity(), [values.name, "state"]) === "changed" && (
+                <Typography
+                  variant="caption"
+                  color="error"
+                  onClick={async () => {
+                    await invoke("accept_script_changes", { name: values.name });
+                    loadIntegrity();
+                  }}
+                >
+                  File changed since it was added, click to accept
+                </Typography>
+              )}
+              {get(integrity(), [values.name, "source_state"]) === "changed" && (
+                <Typography
+                  variant="caption"
+                  color="warning.main"
+                  onClick={async () => {
+                    await invoke("update_script_copy", { name: values.name });
+                    emit("get_scripts", {});
+                  }}
+                >
+                  Original file changed, click to update the copy
+                </Typography>
+              )}
               <List sx={{ flexGrow: 1, overflowY: "auto", maxHeight: "200px" }}>
                 {map(values.args, (argumentValues, index) => (
                   <ListItem
@@ -222,7 +328,11 @@ export const ScriptRunner = () => {
                         ? VISIBILITY.HIDDEN
                         : VISIBILITY.VISIBILE,
                       arguments: join(get(scriptVariables(), [values.name], []), " "),
+                      values: map(values.args, (_, index) =>
+                        get(scriptVariables(), [values.name, index], null),
+                      ),
                       path: values.path,
+                      name: values.name,
                     });
                   }}
                 >
@@ -283,6 +393,7 @@ export const ScriptRunner = () => {
           </ScriptCard>
         ))}
       </ScrollContainer>
+      <PipelineList />
       <Box sx={{ mt: 3, display: "flex", justifyContent: "flex-end", alignItems: "center" }}>
         <IconButton color="primary" size="large" onClick={() => setAddScriptModalOpen(true)}>
           <AddIcon />